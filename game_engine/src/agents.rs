@@ -0,0 +1,415 @@
+use std::{fmt::Display, marker::PhantomData, sync::Arc};
+
+use crossbeam::thread;
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::{
+    clock::{Clock, TimeManager},
+    enhancements::{
+        base::alpha_beta,
+        mcts::{MctsConfig, MonteCarloTree, SearchBudget},
+        opening_book::OpeningBook,
+        search_result::SearchResult,
+        search_state::StopToken,
+        transposition_table::TranspositionTable,
+    },
+    function_types::DepthSearchWithTable,
+    move_finders::{
+        find_best_move, find_best_move_mcts, find_best_move_t_tt_smp, find_best_move_tablebase,
+        human_agent, ponder_t_tt, random_agent,
+    },
+    traits::{ChildStates, EndgameTablebase, ScoreOfState, StateHash, TerminalState},
+};
+
+/// Anything that can pick a move, so games can be played between any two of them
+pub trait Agent<T, M> {
+    fn choose(&mut self, state: &T, color: bool, clock: &Clock) -> M;
+    /// Shown in game logs and results
+    fn name(&self) -> String;
+    /// The search behind the last move, `None` for agents that don't search
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        None
+    }
+}
+
+// ! ======================= Without search =======================
+pub struct RandomAgent {
+    rng: StdRng,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl<T: ChildStates<M> + Copy, M: Copy> Agent<T, M> for RandomAgent {
+    fn choose(&mut self, state: &T, color: bool, _clock: &Clock) -> M {
+        random_agent(*state, color, &mut self.rng)
+    }
+    fn name(&self) -> String {
+        String::from("random")
+    }
+}
+
+/// Lists the moves and reads the index of the chosen one from stdin
+pub struct HumanAgent;
+
+impl<T: ChildStates<M>, M: Display + Copy> Agent<T, M> for HumanAgent {
+    fn choose(&mut self, state: &T, color: bool, _clock: &Clock) -> M {
+        human_agent(state, color)
+    }
+    fn name(&self) -> String {
+        String::from("human")
+    }
+}
+
+// ! ======================= With search =======================
+/// Alpha beta to a fixed depth below every root move, ignores the clock
+pub struct AlphaBetaAgent<M> {
+    depth: isize,
+    last_search: Option<SearchResult<M>>,
+}
+
+impl<M> AlphaBetaAgent<M> {
+    pub fn new(depth: isize) -> Self {
+        Self {
+            depth,
+            last_search: None,
+        }
+    }
+}
+
+impl<T, M> Agent<T, M> for AlphaBetaAgent<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq,
+    M: Copy + Ord,
+{
+    fn choose(&mut self, state: &T, color: bool, _clock: &Clock) -> M {
+        let result = find_best_move(state, self.depth, color, |state, depth, color, context| {
+            alpha_beta(state, depth, color, context, isize::MIN + 1, isize::MAX)
+        });
+        let best_move = result.best_move;
+        self.last_search = Some(result);
+        best_move
+    }
+    fn name(&self) -> String {
+        format!("alpha beta {}ply", self.depth)
+    }
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        self.last_search.as_ref()
+    }
+}
+
+/// Timed iterative deepening with a transposition table, on `threads` threads
+pub struct IterativeDeepeningAgent<M, T, S> {
+    table: Arc<TranspositionTable<M>>,
+    search_function: S,
+    threads: usize,
+    last_search: Option<SearchResult<M>>,
+    state: PhantomData<fn(&T)>,
+}
+
+impl<M, T, S: DepthSearchWithTable<M, T>> IterativeDeepeningAgent<M, T, S> {
+    pub fn new(table: Arc<TranspositionTable<M>>, search_function: S, threads: usize) -> Self {
+        Self {
+            table,
+            search_function,
+            threads: threads.max(1),
+            last_search: None,
+            state: PhantomData,
+        }
+    }
+    pub fn table(&self) -> &Arc<TranspositionTable<M>> {
+        &self.table
+    }
+}
+
+impl<T, M, S> Agent<T, M> for IterativeDeepeningAgent<M, T, S>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Sync + Copy + StateHash,
+    M: Send + Default + Copy + PartialEq,
+    S: DepthSearchWithTable<M, T> + Sync,
+{
+    fn choose(&mut self, state: &T, color: bool, clock: &Clock) -> M {
+        let result = find_best_move_t_tt_smp(
+            state,
+            TimeManager::new(clock),
+            color,
+            &self.table,
+            self.threads,
+            &self.search_function,
+        );
+        let best_move = result.best_move;
+        self.last_search = Some(result);
+        best_move
+    }
+    fn name(&self) -> String {
+        format!("iterative deepening x{}", self.threads)
+    }
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        self.last_search.as_ref()
+    }
+}
+
+/// Plays from `book` while the state is in it and lets `agent` choose after that
+pub struct BookAgent<A, M> {
+    agent: A,
+    book: Arc<OpeningBook<M>>,
+    rng: StdRng,
+    /// Whether the last move came from the book
+    booked: bool,
+}
+
+impl<A, M> BookAgent<A, M> {
+    pub fn new(agent: A, book: Arc<OpeningBook<M>>, seed: u64) -> Self {
+        Self {
+            agent,
+            book,
+            rng: StdRng::seed_from_u64(seed),
+            booked: false,
+        }
+    }
+    pub fn booked(&self) -> bool {
+        self.booked
+    }
+}
+
+impl<A, T, M> Agent<T, M> for BookAgent<A, M>
+where
+    A: Agent<T, M>,
+    T: ChildStates<M> + StateHash,
+    M: Copy + PartialEq,
+{
+    fn choose(&mut self, state: &T, color: bool, clock: &Clock) -> M {
+        match self.book.choose(state, color, &mut self.rng) {
+            Some(book_move) => {
+                self.booked = true;
+                book_move
+            }
+            None => {
+                self.booked = false;
+                self.agent.choose(state, color, clock)
+            }
+        }
+    }
+    fn name(&self) -> String {
+        format!("{} with book", self.agent.name())
+    }
+    /// `None` after a book move
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        match self.booked {
+            true => None,
+            false => self.agent.last_search(),
+        }
+    }
+}
+
+/// Plays the perfect move when `tablebase` knows the state and lets `agent` choose otherwise
+pub struct TablebaseAgent<A, M, B> {
+    agent: A,
+    tablebase: Arc<B>,
+    /// The probe behind the last move, `None` when `agent` chose it
+    last_probe: Option<SearchResult<M>>,
+}
+
+impl<A, M, B> TablebaseAgent<A, M, B> {
+    pub fn new(agent: A, tablebase: Arc<B>) -> Self {
+        Self {
+            agent,
+            tablebase,
+            last_probe: None,
+        }
+    }
+}
+
+impl<A, T, M, B> Agent<T, M> for TablebaseAgent<A, M, B>
+where
+    A: Agent<T, M>,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+    M: Copy,
+    B: EndgameTablebase<T>,
+{
+    fn choose(&mut self, state: &T, color: bool, clock: &Clock) -> M {
+        self.last_probe = find_best_move_tablebase(state, color, &*self.tablebase);
+        match &self.last_probe {
+            Some(result) => result.best_move,
+            None => self.agent.choose(state, color, clock),
+        }
+    }
+    fn name(&self) -> String {
+        format!("{} with tablebase", self.agent.name())
+    }
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        match &self.last_probe {
+            Some(result) => Some(result),
+            None => self.agent.last_search(),
+        }
+    }
+}
+
+/// What was searched while the opponent chose its last move
+#[derive(Clone, Debug)]
+pub struct Ponder<M> {
+    /// The move the opponent was expected to play
+    pub expected: M,
+    /// Whether the opponent played `expected`, only then the search is of any use
+    pub hit: bool,
+    pub search: SearchResult<M>,
+}
+
+/// Thinks on the time of `opponent`. While it chooses, the state after the move expected from it
+/// is searched into `table`, so an agent sharing `table` finds that work when the guess was right.
+pub struct PonderingAgent<A, M, T, S> {
+    opponent: A,
+    table: Arc<TranspositionTable<M>>,
+    search_function: S,
+    last_ponder: Option<Ponder<M>>,
+    state: PhantomData<fn(&T)>,
+}
+
+impl<A, M, T, S: DepthSearchWithTable<M, T>> PonderingAgent<A, M, T, S> {
+    pub fn new(opponent: A, table: Arc<TranspositionTable<M>>, search_function: S) -> Self {
+        Self {
+            opponent,
+            table,
+            search_function,
+            last_ponder: None,
+            state: PhantomData,
+        }
+    }
+    /// `None` when there was nothing to ponder on, like when the table had no expected move
+    pub fn last_ponder(&self) -> Option<&Ponder<M>> {
+        self.last_ponder.as_ref()
+    }
+}
+
+impl<A, T, M, S> Agent<T, M> for PonderingAgent<A, M, T, S>
+where
+    A: Agent<T, M>,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Sync + Copy + StateHash,
+    M: Send + Sync + Default + Copy + PartialEq,
+    S: DepthSearchWithTable<M, T> + Sync,
+{
+    fn choose(&mut self, state: &T, color: bool, clock: &Clock) -> M {
+        self.last_ponder = None;
+        // The expected move is the one the search of the last move left in the table
+        let expected = match self.table.stored_move(state, color) {
+            Some(expected)
+                if !(*state + expected).is_terminal()
+                    && !(*state + expected).child_states(!color).is_empty() =>
+            {
+                expected
+            }
+            _ => return self.opponent.choose(state, color, clock),
+        };
+        let stop = StopToken::new();
+        let table = &self.table;
+        let search_function = &self.search_function;
+        let opponent = &mut self.opponent;
+        let (played, search) = thread::scope(|s| {
+            let ponder = {
+                let stop = stop.clone();
+                s.spawn(move |_| {
+                    ponder_t_tt(&(*state + expected), !color, table, search_function, stop)
+                })
+            };
+            let played = {
+                // A panicking opponent still stops the ponder search, the scope waits for it
+                let _stop = stop.stop_on_drop();
+                opponent.choose(state, color, clock)
+            };
+            (played, ponder.join().unwrap())
+        })
+        .unwrap();
+        self.last_ponder = Some(Ponder {
+            expected,
+            hit: played == expected,
+            search,
+        });
+        played
+    }
+    fn name(&self) -> String {
+        format!("{} (pondering)", self.opponent.name())
+    }
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        self.opponent.last_search()
+    }
+}
+
+/// UCT that keeps its tree between moves and searches for the time on the clock
+pub struct MctsAgent<M, T> {
+    tree: MonteCarloTree<M, T>,
+    last_search: Option<SearchResult<M>>,
+}
+
+impl<M, T> MctsAgent<M, T>
+where
+    T: ChildStates<M> + TerminalState + ScoreOfState + Copy + PartialEq,
+    M: Copy + PartialEq,
+{
+    pub fn new(config: MctsConfig) -> Self {
+        Self {
+            tree: MonteCarloTree::new(config),
+            last_search: None,
+        }
+    }
+}
+
+impl<T, M> Agent<T, M> for MctsAgent<M, T>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq,
+    M: Copy + PartialEq,
+{
+    fn choose(&mut self, state: &T, color: bool, clock: &Clock) -> M {
+        let budget = SearchBudget::Time(clock.move_time());
+        let result = find_best_move_mcts(state, budget, color, &mut self.tree);
+        let best_move = result.best_move;
+        self.last_search = Some(result);
+        best_move
+    }
+    fn name(&self) -> String {
+        String::from("mcts")
+    }
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        self.last_search.as_ref()
+    }
+}
+
+/// Any move finder as an agent, e.g. `find_best_move_t_tt_pvs` with a table it captures
+pub struct SearchAgent<M, F> {
+    name: String,
+    search: F,
+    last_search: Option<SearchResult<M>>,
+}
+
+impl<M, F> SearchAgent<M, F> {
+    pub fn new(name: &str, search: F) -> Self {
+        Self {
+            name: String::from(name),
+            search,
+            last_search: None,
+        }
+    }
+}
+
+impl<T, M, F> Agent<T, M> for SearchAgent<M, F>
+where
+    M: Copy,
+    F: FnMut(&T, bool, &Clock) -> SearchResult<M>,
+{
+    fn choose(&mut self, state: &T, color: bool, clock: &Clock) -> M {
+        let result = (self.search)(state, color, clock);
+        let best_move = result.best_move;
+        self.last_search = Some(result);
+        best_move
+    }
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+    fn last_search(&self) -> Option<&SearchResult<M>> {
+        self.last_search.as_ref()
+    }
+}
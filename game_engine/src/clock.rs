@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+/// Moves the game is expected to last when the time control doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// Kept back for the time between the search stopping and the move being played
+const MOVE_OVERHEAD: Duration = Duration::from_millis(10);
+/// The hard limit is at most this many times the soft limit
+const HARD_LIMIT_FACTOR: u32 = 4;
+/// Bounds of the factor the soft limit is scaled with
+const MIN_SCALE: f64 = 0.25;
+const MAX_SCALE: f64 = 3.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeControl {
+    /// The same fixed time for every move, nothing carries over
+    PerMove(Duration),
+    /// `base` for the whole game, `increment` is added after every move
+    Increment { base: Duration, increment: Duration },
+    /// `base` for every `moves` moves, unused time carries over to the next period
+    MovesToGo { base: Duration, moves: u32 },
+}
+
+/// The clock of one side
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: Duration,
+    moves_to_go: u32,
+}
+
+impl Clock {
+    pub const fn new(control: TimeControl) -> Self {
+        let (control, remaining, moves_to_go) = match control {
+            TimeControl::PerMove(move_time) => (control, move_time, 0),
+            TimeControl::Increment { base, .. } => (control, base, 0),
+            TimeControl::MovesToGo { base, moves } => {
+                let moves = match moves {
+                    0 => 1,
+                    moves => moves,
+                };
+                (TimeControl::MovesToGo { base, moves }, base, moves)
+            }
+        };
+        Self {
+            control,
+            remaining,
+            moves_to_go,
+        }
+    }
+    /// The same fixed time for every move
+    pub const fn per_move(move_time: Duration) -> Self {
+        Self::new(TimeControl::PerMove(move_time))
+    }
+    pub const fn with_increment(base: Duration, increment: Duration) -> Self {
+        Self::new(TimeControl::Increment { base, increment })
+    }
+    pub const fn with_moves_to_go(base: Duration, moves: u32) -> Self {
+        Self::new(TimeControl::MovesToGo { base, moves })
+    }
+    pub const fn control(&self) -> TimeControl {
+        self.control
+    }
+    /// Time left on the clock, for `PerMove` the time of every move
+    pub const fn remaining(&self) -> Duration {
+        self.remaining
+    }
+    /// Moves until the next time control, `None` when there is none
+    pub const fn moves_to_go(&self) -> Option<u32> {
+        match self.control {
+            TimeControl::MovesToGo { .. } => Some(self.moves_to_go),
+            _ => None,
+        }
+    }
+    pub const fn increment(&self) -> Duration {
+        match self.control {
+            TimeControl::Increment { increment, .. } => increment,
+            _ => Duration::ZERO,
+        }
+    }
+    /// How long the agent should think about its next move, for agents that can't adjust their
+    /// search on the fly
+    pub fn move_time(&self) -> Duration {
+        TimeManager::new(self).soft_limit()
+    }
+    /// Charges the time of a move to the clock, false when the time ran out.
+    /// A `PerMove` clock never runs out.
+    pub fn punch(&mut self, elapsed: Duration) -> bool {
+        if let TimeControl::PerMove(_) = self.control {
+            return true;
+        }
+        if elapsed > self.remaining {
+            self.remaining = Duration::ZERO;
+            return false;
+        }
+        self.remaining -= elapsed;
+        match self.control {
+            TimeControl::Increment { increment, .. } => self.remaining += increment,
+            TimeControl::MovesToGo { base, moves } => {
+                self.moves_to_go -= 1;
+                if self.moves_to_go == 0 {
+                    self.moves_to_go = moves;
+                    self.remaining += base;
+                }
+            }
+            TimeControl::PerMove(_) => (),
+        }
+        true
+    }
+}
+
+/// Splits the time on a clock into limits for one move. Iterative deepening doesn't start a new
+/// iteration after the soft limit and aborts the running one at the hard limit.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeManager {
+    soft: Duration,
+    hard: Duration,
+    /// The soft limit is scaled with this, it grows when the best move changes and shrinks while
+    /// one move dominates
+    scale: f64,
+    /// False for a fixed time per move, which is always used in full
+    flexible: bool,
+    /// A best move this far ahead of the second best, from the view of the mover, dominates
+    pub dominance_margin: isize,
+}
+
+impl TimeManager {
+    pub fn new(clock: &Clock) -> Self {
+        if let TimeControl::PerMove(move_time) = clock.control() {
+            return Self::fixed(move_time);
+        }
+        let usable = clock.remaining().saturating_sub(MOVE_OVERHEAD);
+        let moves = clock.moves_to_go().unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let soft = usable / moves + clock.increment() * 3 / 4;
+        // Never bet more than half the clock on one move, unless it is the last before the control
+        let hard = match moves {
+            1 => usable,
+            _ => (soft * HARD_LIMIT_FACTOR).min(usable / 2),
+        };
+        Self {
+            soft: soft.min(hard),
+            hard,
+            scale: 1.0,
+            flexible: true,
+            dominance_margin: 100,
+        }
+    }
+    /// Searches exactly `move_time`
+    pub fn fixed(move_time: Duration) -> Self {
+        Self {
+            soft: move_time,
+            hard: move_time,
+            scale: 1.0,
+            flexible: false,
+            dominance_margin: 100,
+        }
+    }
+    pub fn soft_limit(&self) -> Duration {
+        self.soft.mul_f64(self.scale).min(self.hard)
+    }
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+    /// Called after every completed iteration with whether its best move differs from the last
+    /// one and how far it is ahead of the second best move
+    pub fn iteration_done(&mut self, best_move_changed: bool, lead: isize) {
+        if !self.flexible {
+            return;
+        }
+        self.scale = match (best_move_changed, lead >= self.dominance_margin) {
+            (true, _) => self.scale * 1.5,
+            (false, true) => self.scale * 0.5,
+            // A stable best move gives back the extensions
+            (false, false) if self.scale > 1.0 => (self.scale * 0.8).max(1.0),
+            (false, false) => self.scale,
+        }
+        .clamp(MIN_SCALE, MAX_SCALE);
+    }
+    /// Whether a new iteration should start after `elapsed`
+    pub fn keep_searching(&self, elapsed: Duration) -> bool {
+        elapsed < self.soft_limit()
+    }
+}
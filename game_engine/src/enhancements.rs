@@ -1,4 +1,19 @@
+pub mod aspiration_window;
 pub mod base;
+pub mod expectimax;
 pub mod iterative_deepening;
+pub mod mate_score;
+pub mod mcts;
+pub mod move_ordering;
+pub mod mtdf;
+pub mod multi_player;
+pub mod opening_book;
+pub mod quiescence;
+pub mod replacement_policy;
+pub mod search_result;
+pub mod search_state;
+pub mod selective_search;
+pub mod table_persistence;
+pub mod tablebase;
 pub mod transposition_state_type;
 pub mod transposition_table;
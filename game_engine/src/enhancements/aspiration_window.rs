@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+use super::{
+    super::{
+        function_types::WindowSearchFunctionWithTable,
+        traits::{ChildStates, ScoreOfState, StateHash, TerminalState},
+    },
+    search_state::{SearchContext, StopToken},
+    transposition_table::TranspositionTable,
+};
+
+/// How wide the window around the previous score starts and how fast it grows on a fail
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AspirationWindow {
+    /// Distance between the previous score and either bound of the first window
+    pub size: isize,
+    /// The distance on the failing side is multiplied by this before the re-search
+    pub growth: isize,
+}
+
+impl Default for AspirationWindow {
+    fn default() -> Self {
+        Self {
+            size: 25,
+            growth: 4,
+        }
+    }
+}
+
+/// Searches `depth` in a window centred on `previous`, widening and re-searching until the score
+/// falls inside of it. Scores and bounds are from the view of `true`.
+#[allow(clippy::too_many_arguments)]
+pub fn aspiration_search<M, T>(
+    state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    previous: isize,
+    window: AspirationWindow,
+    search_function: WindowSearchFunctionWithTable<M, T>,
+) -> Option<isize>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + StateHash,
+{
+    let mut below = window.size.max(1);
+    let mut above = window.size.max(1);
+    loop {
+        let alpha = previous.saturating_sub(below).max(isize::MIN + 1);
+        let beta = previous.saturating_add(above);
+        let score = search_function(state, depth, color, table, context, alpha, beta)?;
+        // ! ================ Fail low or fail high, widen that side ================
+        if score <= alpha && alpha > isize::MIN + 1 {
+            below = below.saturating_mul(window.growth.max(2));
+        } else if score >= beta && beta < isize::MAX {
+            above = above.saturating_mul(window.growth.max(2));
+        } else {
+            return Some(score);
+        }
+    }
+}
+
+/// Timed ID where every depth after the first is searched with an aspiration window.
+/// Returns the score of the last completed iteration, `None` if not even the first one finished.
+pub fn iterative_deepening_aspiration_t_tt<M, T>(
+    state: &T,
+    color: bool,
+    max_time: Duration,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    window: AspirationWindow,
+    search_function: WindowSearchFunctionWithTable<M, T>,
+) -> Option<isize>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + StateHash,
+{
+    let start = Instant::now();
+    context.watching(StopToken::with_deadline(start + max_time), |context| {
+        let mut score =
+            search_function(state, 1, color, table, context, isize::MIN + 1, isize::MAX)?;
+        context.record_depth(1);
+
+        let mut depth = 2;
+        while start.elapsed() < max_time {
+            let Some(iteration_score) = aspiration_search(
+                state,
+                depth,
+                color,
+                table,
+                context,
+                score,
+                window,
+                search_function,
+            ) else {
+                break;
+            };
+            score = iteration_score;
+            context.record_depth(depth);
+            depth += 1;
+        }
+        Some(score)
+    })
+}
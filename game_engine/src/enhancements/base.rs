@@ -3,7 +3,17 @@ use std::cmp::{max, min};
 use std::fmt::Display;
 
 use super::{
-    super::traits::{ChildStates, ScoreOfState, StateHash, TerminalState},
+    super::{
+        function_types::LeafFunction,
+        traits::{
+            ChildStates, EndgameTablebase, NoisyMoves, ScoreOfState, StateHash, TerminalState,
+        },
+    },
+    mate_score::add_plies,
+    move_ordering::MoveOrdering,
+    quiescence::{quiescence, static_leaf, static_score},
+    search_state::SearchContext,
+    tablebase::tablebase_score,
     transposition_state_type::TranspositionStateType,
     transposition_table::TranspositionTable,
 };
@@ -20,57 +30,192 @@ pub fn replay_game<M: Copy + Display, T: ChildStates<M> + Copy + Display>(
     }
 }
 
-pub fn nega<M, T>(&state: &T, depth: isize, color: bool, mut alpha: isize, beta: isize) -> isize
+pub fn nega<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+{
+    nega_ordered(
+        &state,
+        depth,
+        color,
+        &mut MoveOrdering::disabled(),
+        context,
+        alpha,
+        beta,
+    )
+}
+
+pub fn nega_ordered<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+{
+    nega_leaf(
+        &state,
+        depth,
+        color,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &static_leaf,
+    )
+}
+
+/// `nega_ordered` that extends its leaves with a quiescence search
+pub fn nega_quiescence<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + NoisyMoves<M> + Copy,
+{
+    nega_leaf(
+        &state,
+        depth,
+        color,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &quiescence,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn nega_leaf<M, T, L>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    mut alpha: isize,
+    beta: isize,
+    leaf: &L,
+) -> Option<isize>
 where
     M: Copy + Ord,
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+    L: LeafFunction<T>,
 {
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
     if 0 == depth || state.is_terminal() {
-        return match color {
-            true => state.score_of(),
-            false => -state.score_of(),
-        };
+        return leaf(&state, color, ordering.ply(), context, alpha, beta);
     }
 
-    let moves = state.child_states(color);
+    let mut moves = state.child_states(color);
+    ordering.order(&mut moves, None);
     let mut score = isize::MIN + 1;
     for new_move in moves {
         let child = state + new_move;
-        let value = -nega(&child, depth - 1, !color, -beta, -alpha);
+        ordering.push(new_move);
+        let value = nega_leaf(
+            &child,
+            depth - 1,
+            !color,
+            ordering,
+            context,
+            -beta,
+            -alpha,
+            leaf,
+        );
+        ordering.pop();
+        let value = -value?;
         score = max(score, value);
         alpha = max(alpha, score);
         if alpha >= beta {
-            return alpha;
+            ordering.cutoff(new_move, depth);
+            return Some(alpha);
         }
     }
-    score
+    Some(score)
 }
 
 pub fn alpha_beta<M, T>(
     &state: &T,
     depth: isize,
     color: bool,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+{
+    alpha_beta_ordered(
+        &state,
+        depth,
+        color,
+        &mut MoveOrdering::disabled(),
+        context,
+        alpha,
+        beta,
+    )
+}
+
+pub fn alpha_beta_ordered<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
     mut alpha: isize,
     mut beta: isize,
-) -> isize
+) -> Option<isize>
 where
     M: Copy + Ord,
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
 {
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
     if 0 == depth || state.is_terminal() {
-        return state.score_of();
+        return Some(add_plies(state.score_of(), ordering.ply() as isize));
     }
-    let moves = state.child_states(color);
+    let mut moves = state.child_states(color);
+    ordering.order(&mut moves, None);
 
     let mut score: isize;
     if color {
         score = isize::MIN + 1;
         for new_move in moves {
             let child = state + new_move;
-            let value = alpha_beta(&child, depth - 1, !color, alpha, beta);
+            ordering.push(new_move);
+            let value =
+                alpha_beta_ordered(&child, depth - 1, !color, ordering, context, alpha, beta);
+            ordering.pop();
+            let value = value?;
             score = max(score, value);
             alpha = max(alpha, score);
             if score >= beta {
+                ordering.cutoff(new_move, depth);
                 break;
             }
         }
@@ -78,30 +223,64 @@ where
         score = isize::MAX;
         for child in moves {
             let child_state = state + child;
-            let child_value = alpha_beta(&child_state, depth - 1, !color, alpha, beta);
+            ordering.push(child);
+            let child_value = alpha_beta_ordered(
+                &child_state,
+                depth - 1,
+                !color,
+                ordering,
+                context,
+                alpha,
+                beta,
+            );
+            ordering.pop();
+            let child_value = child_value?;
             score = min(score, child_value);
             beta = min(score, beta);
             if score <= alpha {
+                ordering.cutoff(child, depth);
                 break;
             }
         }
     }
-    score
+    Some(score)
 }
 
 pub fn alpha_beta_bitwise<M, T>(
     &state: &T,
     depth: isize,
     color: usize,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+{
+    alpha_beta_bitwise_ply(&state, depth, color, 0, context, alpha, beta)
+}
+
+fn alpha_beta_bitwise_ply<M, T>(
+    &state: &T,
+    depth: isize,
+    color: usize,
+    ply: usize,
+    context: &mut SearchContext,
     mut alpha: isize,
     mut beta: isize,
-) -> isize
+) -> Option<isize>
 where
     M: Copy,
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
 {
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
     if 0 == depth || state.is_terminal() {
-        return state.score_of();
+        // Scored from the view of true, like `alpha_beta`
+        return Some(static_score(&state, true, ply, alpha, beta));
     }
     let vals = [false, true];
     let moves = state.child_states(vals[color]);
@@ -114,58 +293,82 @@ where
         state,
         depth,
         color ^ 1,
+        ply + 1,
+        context,
         &mut alpha,
         &mut beta,
-    );
-    value
+    )?;
+    Some(value)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn ab_bitwise_min_part<T, M>(
     value: &mut isize,
     moves: &Vec<M>,
     state: T,
     depth: isize,
     color: usize,
+    ply: usize,
+    context: &mut SearchContext,
     alpha: &mut isize,
     beta: &mut isize,
-) where
+) -> Option<()>
+where
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
     M: Copy,
 {
     *value = isize::MAX;
     for new_move in moves {
         let child_state = state + *new_move;
-        let child_value = alpha_beta_bitwise(&child_state, depth - 1, color, *alpha, *beta);
+        let child_value =
+            alpha_beta_bitwise_ply(&child_state, depth - 1, color, ply, context, *alpha, *beta)?;
         *value = min(*value, child_value);
         *beta = min(*value, *beta);
         if *value <= *alpha {
             break;
         }
     }
+    Some(())
 }
-type BitwisePart<T, M> = fn(&mut isize, &Vec<M>, T, isize, usize, &mut isize, &mut isize);
+type BitwisePart<T, M> = fn(
+    &mut isize,
+    &Vec<M>,
+    T,
+    isize,
+    usize,
+    usize,
+    &mut SearchContext,
+    &mut isize,
+    &mut isize,
+) -> Option<()>;
+#[allow(clippy::too_many_arguments)]
 fn ab_bitwise_max_part<T, M>(
     value: &mut isize,
     moves: &Vec<M>,
     state: T,
     depth: isize,
     color: usize,
+    ply: usize,
+    context: &mut SearchContext,
     alpha: &mut isize,
     beta: &mut isize,
-) where
+) -> Option<()>
+where
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
     M: Copy,
 {
     *value = isize::MIN + 1;
     for new_move in moves {
         let child_state = state + *new_move;
-        let child_value = alpha_beta_bitwise(&child_state, depth - 1, color, *alpha, *beta);
+        let child_value =
+            alpha_beta_bitwise_ply(&child_state, depth - 1, color, ply, context, *alpha, *beta)?;
         *value = max(*value, child_value);
         *alpha = max(*alpha, *value);
         if *value >= *beta {
             break;
         }
     }
+    Some(())
 }
 
 // ! ====================== This is the one =====================
@@ -173,51 +376,189 @@ pub fn nega_with_table<M, T>(
     &state: &T,
     depth: isize,
     color: bool,
-    table: &mut TranspositionTable<M>,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    nega_with_table_ordered(
+        &state,
+        depth,
+        color,
+        table,
+        &mut MoveOrdering::disabled(),
+        context,
+        alpha,
+        beta,
+    )
+}
+
+/// `nega_with_table` with the window and the score from the view of `true`, the form
+/// `WindowSearchFunctionWithTable` expects
+pub fn nega_with_table_for_true<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    match color {
+        true => nega_with_table(&state, depth, color, table, context, alpha, beta),
+        false => nega_with_table(&state, depth, color, table, context, -beta, -alpha).map(|v| -v),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn nega_with_table_ordered<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    nega_with_table_leaf(
+        &state,
+        depth,
+        color,
+        table,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &static_leaf,
+    )
+}
+
+/// `nega_with_table_ordered` that extends its leaves with a quiescence search
+#[allow(clippy::too_many_arguments)]
+pub fn nega_with_table_quiescence<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + NoisyMoves<M> + Copy + StateHash,
+{
+    nega_with_table_leaf(
+        &state,
+        depth,
+        color,
+        table,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &quiescence,
+    )
+}
+
+/// `nega_with_table_ordered` that takes the scores of its leaves from `tablebase` if it can
+#[allow(clippy::too_many_arguments)]
+pub fn nega_with_table_tablebase<M, T, B>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    tablebase: &B,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+    B: EndgameTablebase<T>,
+{
+    nega_with_table_leaf(
+        &state,
+        depth,
+        color,
+        table,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &|state: &T, color, ply, _context: &mut SearchContext, alpha, beta| {
+            Some(tablebase_score(state, tablebase, color, ply, alpha, beta))
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn nega_with_table_leaf<M, T, L>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
     mut alpha: isize,
     mut beta: isize,
-) -> isize
+    leaf: &L,
+) -> Option<isize>
 where
     M: Default + Copy + Eq + Ord,
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+    L: LeafFunction<T>,
 {
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
     // ! ================= Save alpha ===================
     let original_alpha = alpha;
 
     // ! =================== Check TT ==================
-    let (stored_value, stored_depth, state_type, stored_best_move) = table.get(state.hash(color));
+    let (stored_value, stored_depth, state_type, stored_best_move) =
+        table.probe(&state, color, ordering.ply());
     if state_type != TranspositionStateType::Unknown && stored_depth >= depth {
         match state_type {
-            TranspositionStateType::Exact => return stored_value,
+            TranspositionStateType::Exact => {
+                table.record_cutoff(state_type);
+                return Some(stored_value);
+            }
             TranspositionStateType::LowerBound => alpha = max(alpha, stored_value),
             TranspositionStateType::UpperBound => beta = min(beta, stored_value),
             _ => panic!(),
         }
         if alpha >= beta {
-            return stored_value;
+            table.record_cutoff(state_type);
+            return Some(stored_value);
         }
     }
 
     if 0 == depth || state.is_terminal() {
-        return match color {
-            true => state.score_of(),
-            false => -state.score_of(),
-        };
+        return leaf(&state, color, ordering.ply(), context, alpha, beta);
     }
 
-    let children = state.child_states(color);
-    let mut ordered_children = children.clone();
-
     // ! ===================== Check TT Move First ====================
-    if state_type != TranspositionStateType::Unknown {
-        for (index, child) in children.into_iter().enumerate() {
-            if child == stored_best_move {
-                let temp = ordered_children.remove(index);
-                ordered_children.insert(0, temp);
-                break;
-            }
-        }
-    }
+    let mut ordered_children = state.child_states(color);
+    let tt_move = (state_type != TranspositionStateType::Unknown).then_some(stored_best_move);
+    ordering.order(&mut ordered_children, tt_move);
 
     let mut value: isize;
     let mut best_move = M::default();
@@ -225,13 +566,27 @@ where
     // !========================== Nega Alpha-Beta ===========================
     for child in ordered_children {
         let child_state = state + child;
-        let child_value = -nega_with_table(&child_state, depth - 1, !color, table, -beta, -alpha);
+        ordering.push(child);
+        let child_value = nega_with_table_leaf(
+            &child_state,
+            depth - 1,
+            !color,
+            table,
+            ordering,
+            context,
+            -beta,
+            -alpha,
+            leaf,
+        );
+        ordering.pop();
+        let child_value = -child_value?;
         if value < child_value {
             best_move = child;
         }
         value = max(value, child_value);
         alpha = max(alpha, value);
         if alpha >= beta {
+            ordering.cutoff(child, depth);
             break;
         }
     }
@@ -244,55 +599,184 @@ where
     } else {
         flag = TranspositionStateType::Exact;
     }
-    table.add(state.hash(color), (value, depth, flag, best_move));
+    table.store(
+        &state,
+        color,
+        ordering.ply(),
+        (value, depth, flag, best_move),
+    );
 
-    value
+    Some(value)
 }
 
 pub fn alpha_beta_with_table<M, T>(
     &state: &T,
     depth: isize,
     color: bool,
-    table: &mut TranspositionTable<M>,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    alpha_beta_with_table_ordered(
+        &state,
+        depth,
+        color,
+        table,
+        &mut MoveOrdering::disabled(),
+        context,
+        alpha,
+        beta,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn alpha_beta_with_table_ordered<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    alpha_beta_with_table_leaf(
+        &state,
+        depth,
+        color,
+        table,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &static_leaf,
+    )
+}
+
+/// `alpha_beta_with_table_ordered` that extends its leaves with a quiescence search
+#[allow(clippy::too_many_arguments)]
+pub fn alpha_beta_with_table_quiescence<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + NoisyMoves<M> + Copy + StateHash,
+{
+    alpha_beta_with_table_leaf(
+        &state,
+        depth,
+        color,
+        table,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &quiescence,
+    )
+}
+
+/// `alpha_beta_with_table_ordered` that takes the scores of its leaves from `tablebase` if it can
+#[allow(clippy::too_many_arguments)]
+pub fn alpha_beta_with_table_tablebase<M, T, B>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    tablebase: &B,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+    B: EndgameTablebase<T>,
+{
+    alpha_beta_with_table_leaf(
+        &state,
+        depth,
+        color,
+        table,
+        ordering,
+        context,
+        alpha,
+        beta,
+        &|state: &T, color, ply, _context: &mut SearchContext, alpha, beta| {
+            Some(tablebase_score(state, tablebase, color, ply, alpha, beta))
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn alpha_beta_with_table_leaf<M, T, L>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
     mut alpha: isize,
     mut beta: isize,
-) -> isize
+    leaf: &L,
+) -> Option<isize>
 where
     M: Default + Copy + Eq + Ord,
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+    L: LeafFunction<T>,
 {
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
     let original_alpha = alpha;
+    let original_beta = beta;
 
     // Check TT
-    let (stored_value, stored_depth, state_type, stored_best_move) = table.get(state.hash(color));
+    let (stored_value, stored_depth, state_type, stored_best_move) =
+        table.probe(&state, color, ordering.ply());
     if state_type != TranspositionStateType::Unknown && stored_depth >= depth {
         match state_type {
-            TranspositionStateType::Exact => return stored_value,
+            TranspositionStateType::Exact => {
+                table.record_cutoff(state_type);
+                return Some(stored_value);
+            }
             TranspositionStateType::LowerBound => alpha = max(alpha, stored_value),
             TranspositionStateType::UpperBound => beta = min(beta, stored_value),
             _ => panic!(),
         }
         if alpha >= beta {
-            return stored_value;
+            table.record_cutoff(state_type);
+            return Some(stored_value);
         }
     }
 
     if 0 == depth || state.is_terminal() {
-        return state.score_of();
+        return match color {
+            true => leaf(&state, true, ordering.ply(), context, alpha, beta),
+            false => leaf(&state, false, ordering.ply(), context, -beta, -alpha).map(|v| -v),
+        };
     }
 
-    let children = state.child_states(color);
-    let mut ordered_children = children.clone();
-
-    if state_type != TranspositionStateType::Unknown {
-        for (index, child) in children.into_iter().enumerate() {
-            if child == stored_best_move {
-                let temp = ordered_children.remove(index);
-                ordered_children.insert(0, temp);
-                break;
-            }
-        }
-    }
+    let mut ordered_children = state.child_states(color);
+    let tt_move = (state_type != TranspositionStateType::Unknown).then_some(stored_best_move);
+    ordering.order(&mut ordered_children, tt_move);
 
     let mut value: isize;
     let mut best_move = M::default();
@@ -300,14 +784,27 @@ where
         value = isize::MIN + 1;
         for child in ordered_children {
             let child_state = state + child;
-            let child_value =
-                alpha_beta_with_table(&child_state, depth - 1, !color, table, alpha, beta);
+            ordering.push(child);
+            let child_value = alpha_beta_with_table_leaf(
+                &child_state,
+                depth - 1,
+                !color,
+                table,
+                ordering,
+                context,
+                alpha,
+                beta,
+                leaf,
+            );
+            ordering.pop();
+            let child_value = child_value?;
             if value < child_value {
                 best_move = child;
             }
             value = max(value, child_value);
             alpha = max(alpha, value);
             if value >= beta {
+                ordering.cutoff(child, depth);
                 break;
             }
         }
@@ -315,46 +812,83 @@ where
         value = isize::MAX;
         for child in ordered_children {
             let child_state = state + child;
-            let child_value =
-                alpha_beta_with_table(&child_state, depth - 1, !color, table, alpha, beta);
+            ordering.push(child);
+            let child_value = alpha_beta_with_table_leaf(
+                &child_state,
+                depth - 1,
+                !color,
+                table,
+                ordering,
+                context,
+                alpha,
+                beta,
+                leaf,
+            );
+            ordering.pop();
+            let child_value = child_value?;
             if value > child_value {
                 best_move = child;
             }
             value = min(value, child_value);
             beta = min(beta, value);
             if value <= alpha {
+                ordering.cutoff(child, depth);
                 break;
             }
         }
     }
+    // The loop narrows alpha or beta, the bound type is decided by the window it was called with
     let flag: TranspositionStateType;
     if value <= original_alpha {
         flag = TranspositionStateType::UpperBound;
-    } else if value >= beta {
+    } else if value >= original_beta {
         flag = TranspositionStateType::LowerBound;
     } else {
         flag = TranspositionStateType::Exact;
     }
-    table.add(state.hash(color), (value, depth, flag, best_move));
+    table.store(
+        &state,
+        color,
+        ordering.ply(),
+        (value, depth, flag, best_move),
+    );
 
-    value
+    Some(value)
 }
 pub fn nega_scout<M, T>(
     state: &T,
     depth: isize,
     color: bool,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+{
+    nega_scout_ply(state, depth, color, 0, context, alpha, beta)
+}
+
+fn nega_scout_ply<M, T>(
+    state: &T,
+    depth: isize,
+    color: bool,
+    ply: usize,
+    context: &mut SearchContext,
     mut alpha: isize,
     beta: isize,
-) -> isize
+) -> Option<isize>
 where
     M: Copy + Ord,
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
 {
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
     if depth == 0 || state.is_terminal() {
-        return match color {
-            true => state.score_of(),
-            false => -state.score_of(),
-        };
+        return Some(static_score(state, color, ply, alpha, beta));
     }
 
     let mut score = isize::MIN + 1;
@@ -363,19 +897,172 @@ where
 
     for new_move in moves {
         let child = *state + new_move;
-        let value = -nega_scout(&child, depth - 1, !color, -n, -alpha);
+        let value = -nega_scout_ply(&child, depth - 1, !color, ply + 1, context, -n, -alpha)?;
         if value > score {
             if n == beta || depth <= 2 {
                 score = value;
             } else {
-                score = -nega_scout(state, depth - 1, !color, -beta, -value);
+                score =
+                    -nega_scout_ply(&child, depth - 1, !color, ply + 1, context, -beta, -value)?;
             }
         }
         alpha = max(alpha, score);
         if alpha >= beta {
-            return alpha;
+            return Some(alpha);
         }
         n = alpha + 1;
     }
-    score
+    Some(score)
+}
+
+/// Principal variation search, the first move gets the full window and the rest a null window.
+/// A move that beats alpha on the null window is searched again with the full window.
+pub fn pvs_with_table<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    pvs_with_table_ordered(
+        &state,
+        depth,
+        color,
+        table,
+        &mut MoveOrdering::disabled(),
+        context,
+        alpha,
+        beta,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pvs_with_table_ordered<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    mut alpha: isize,
+    mut beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
+    let original_alpha = alpha;
+
+    // ! =================== Check TT ==================
+    let (stored_value, stored_depth, state_type, stored_best_move) =
+        table.probe(&state, color, ordering.ply());
+    if state_type != TranspositionStateType::Unknown && stored_depth >= depth {
+        match state_type {
+            TranspositionStateType::Exact => {
+                table.record_cutoff(state_type);
+                return Some(stored_value);
+            }
+            TranspositionStateType::LowerBound => alpha = max(alpha, stored_value),
+            TranspositionStateType::UpperBound => beta = min(beta, stored_value),
+            _ => panic!(),
+        }
+        if alpha >= beta {
+            table.record_cutoff(state_type);
+            return Some(stored_value);
+        }
+    }
+
+    if 0 == depth || state.is_terminal() {
+        return Some(static_score(&state, color, ordering.ply(), alpha, beta));
+    }
+
+    let mut ordered_children = state.child_states(color);
+    let tt_move = (state_type != TranspositionStateType::Unknown).then_some(stored_best_move);
+    ordering.order(&mut ordered_children, tt_move);
+
+    let mut value = isize::MIN + 1;
+    let mut best_move = M::default();
+    // ! ===================== Scout with a null window ====================
+    for (index, child) in ordered_children.into_iter().enumerate() {
+        let child_state = state + child;
+        ordering.push(child);
+        let mut child_value;
+        if index == 0 {
+            child_value = pvs_with_table_ordered(
+                &child_state,
+                depth - 1,
+                !color,
+                table,
+                ordering,
+                context,
+                -beta,
+                -alpha,
+            )
+            .map(|v| -v);
+        } else {
+            child_value = pvs_with_table_ordered(
+                &child_state,
+                depth - 1,
+                !color,
+                table,
+                ordering,
+                context,
+                -alpha - 1,
+                -alpha,
+            )
+            .map(|v| -v);
+            // Fail high on the null window, search the child again to get its real value
+            if matches!(child_value, Some(v) if alpha < v && v < beta) {
+                child_value = pvs_with_table_ordered(
+                    &child_state,
+                    depth - 1,
+                    !color,
+                    table,
+                    ordering,
+                    context,
+                    -beta,
+                    -alpha,
+                )
+                .map(|v| -v);
+            }
+        }
+        ordering.pop();
+        let child_value = child_value?;
+        if value < child_value {
+            best_move = child;
+        }
+        value = max(value, child_value);
+        alpha = max(alpha, value);
+        if alpha >= beta {
+            ordering.cutoff(child, depth);
+            break;
+        }
+    }
+    // ! ==================== Store in TT ===================
+    let flag: TranspositionStateType;
+    if value <= original_alpha {
+        flag = TranspositionStateType::UpperBound;
+    } else if value >= beta {
+        flag = TranspositionStateType::LowerBound;
+    } else {
+        flag = TranspositionStateType::Exact;
+    }
+    table.store(
+        &state,
+        color,
+        ordering.ply(),
+        (value, depth, flag, best_move),
+    );
+
+    Some(value)
 }
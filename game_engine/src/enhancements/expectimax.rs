@@ -0,0 +1,416 @@
+use std::cmp::{max, min};
+
+use super::{
+    super::traits::{ChanceStates, ChildStates, ScoreOfState, TerminalState},
+    quiescence::static_score,
+    search_state::SearchContext,
+};
+
+/// Negamax where chance nodes are worth the weighted average of their outcomes.
+/// Chance events don't use up depth, `color` is the side that moves after them.
+pub fn expectimax<M, C, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    context: &mut SearchContext,
+) -> Option<isize>
+where
+    M: Copy,
+    C: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    expectimax_ply(&state, depth, color, 0, context)
+}
+
+/// Only moves count as a ply, chance events don't
+fn expectimax_ply<M, C, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    ply: usize,
+    context: &mut SearchContext,
+) -> Option<isize>
+where
+    M: Copy,
+    C: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
+    if 0 == depth || state.is_terminal() {
+        return Some(static_score(&state, color, ply, isize::MIN + 1, isize::MAX));
+    }
+
+    let outcomes = possible_outcomes(&state, color);
+    if !outcomes.is_empty() {
+        let mut total = 0;
+        let mut weighted_sum = 0;
+        for (outcome, weight) in outcomes {
+            let value = expectimax_ply(&(state + outcome), depth, color, ply, context)?;
+            total += weight as i128;
+            weighted_sum += weight as i128 * value as i128;
+        }
+        return Some((weighted_sum / total) as isize);
+    }
+
+    let mut score = isize::MIN + 1;
+    for new_move in state.child_states(color) {
+        let value = -expectimax_ply(&(state + new_move), depth - 1, !color, ply + 1, context)?;
+        score = max(score, value);
+    }
+    Some(score)
+}
+
+/// Alpha beta with Star1 pruning of chance nodes, which stops searching outcomes once the
+/// bounds of `ChanceStates::score_bounds` show the average can't end up inside the window
+pub fn star1<M, C, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy,
+    C: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    star(&state, depth, color, 0, context, alpha, beta, false)
+}
+
+/// `star1` that first probes one move of every outcome, which gives better lower bounds than
+/// `score_bounds` and can cut the chance node off before any outcome is fully searched
+pub fn star2<M, C, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Copy,
+    C: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    star(&state, depth, color, 0, context, alpha, beta, true)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn star<M, C, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    ply: usize,
+    context: &mut SearchContext,
+    mut alpha: isize,
+    beta: isize,
+    probe: bool,
+) -> Option<isize>
+where
+    M: Copy,
+    C: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
+    if 0 == depth || state.is_terminal() {
+        return Some(static_score(&state, color, ply, alpha, beta));
+    }
+
+    let outcomes = possible_outcomes(&state, color);
+    if !outcomes.is_empty() {
+        return chance_node(
+            &state, depth, color, ply, context, alpha, beta, probe, outcomes,
+        );
+    }
+
+    let mut score = isize::MIN + 1;
+    for new_move in state.child_states(color) {
+        let value = -star(
+            &(state + new_move),
+            depth - 1,
+            !color,
+            ply + 1,
+            context,
+            -beta,
+            -alpha,
+            probe,
+        )?;
+        score = max(score, value);
+        alpha = max(alpha, score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    Some(score)
+}
+
+/// Works on sums of weight times value, so the window is multiplied by the total weight instead
+/// of dividing the values
+#[allow(clippy::too_many_arguments)]
+fn chance_node<M, C, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    ply: usize,
+    context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+    probe: bool,
+    outcomes: Vec<(C, u32)>,
+) -> Option<isize>
+where
+    M: Copy,
+    C: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    let (lowest, highest) = state.score_bounds();
+    let (lowest, highest) = match color {
+        true => (lowest as i128, highest as i128),
+        false => (-highest as i128, -lowest as i128),
+    };
+    // Outside the bounds the window is empty for every outcome, and a search with an empty
+    // window returns a value it hasn't proven
+    if beta as i128 <= lowest {
+        return Some(beta);
+    }
+    if alpha as i128 >= highest {
+        return Some(alpha);
+    }
+    let total: i128 = outcomes.iter().map(|&(_, weight)| weight as i128).sum();
+    let alpha_sum = alpha as i128 * total;
+    let beta_sum = beta as i128 * total;
+
+    let mut lower_bounds = vec![lowest; outcomes.len()];
+    // ! ================== Star2 probing ==================
+    if probe {
+        let mut lower_sum: i128 = outcomes.iter().map(|&(_, w)| w as i128 * lowest).sum();
+        for (index, &(outcome, weight)) in outcomes.iter().enumerate() {
+            let child = state + outcome;
+            if child.is_terminal() || child.is_chance(color) {
+                continue;
+            }
+            let Some(&first_move) = child.child_states(color).first() else {
+                continue;
+            };
+            let weight = weight as i128;
+            let others = lower_sum - weight * lowest;
+            let child_beta = min(ceil_div(beta_sum - others, weight), highest);
+            let value = -star(
+                &(child + first_move),
+                depth - 1,
+                !color,
+                ply + 1,
+                context,
+                -child_beta as isize,
+                -lowest as isize,
+                probe,
+            )?;
+            // One move is a lower bound for the outcome, unless it failed low. The window is
+            // never empty, `lower_sum < beta_sum` keeps `child_beta` above `lowest`.
+            lower_bounds[index] = max(lowest, value as i128);
+            lower_sum = others + weight * lower_bounds[index];
+            if lower_sum >= beta_sum {
+                return Some(beta);
+            }
+        }
+    }
+
+    // ! ================== Star1 search ==================
+    let mut searched_sum: i128 = 0;
+    let mut lower_rest: i128 = (0..outcomes.len())
+        .map(|i| outcomes[i].1 as i128 * lower_bounds[i])
+        .sum();
+    let mut upper_rest: i128 = total * highest;
+    for (index, &(outcome, weight)) in outcomes.iter().enumerate() {
+        let weight = weight as i128;
+        lower_rest -= weight * lower_bounds[index];
+        upper_rest -= weight * highest;
+        // The probe already proved the highest score, its window would be empty
+        if lower_bounds[index] >= highest {
+            searched_sum += weight * highest;
+            if searched_sum + lower_rest >= beta_sum {
+                return Some(beta);
+            }
+            continue;
+        }
+        let child_alpha = max(
+            (alpha_sum - searched_sum - upper_rest).div_euclid(weight),
+            lower_bounds[index],
+        );
+        let child_beta = min(
+            ceil_div(beta_sum - searched_sum - lower_rest, weight),
+            highest,
+        );
+        let value = star(
+            &(state + outcome),
+            depth,
+            color,
+            ply,
+            context,
+            child_alpha as isize,
+            child_beta as isize,
+            probe,
+        )?;
+        searched_sum += weight * value as i128;
+        if searched_sum + lower_rest >= beta_sum {
+            return Some(beta);
+        }
+        if searched_sum + upper_rest <= alpha_sum {
+            return Some(alpha);
+        }
+    }
+    Some((searched_sum / total) as isize)
+}
+
+/// The outcomes with a weight, empty when `color` moves without a chance event first. A chance
+/// event where every weight is 0 can't happen, so the state is searched like any other.
+fn possible_outcomes<C, T: ChanceStates<C>>(state: &T, color: bool) -> Vec<(C, u32)> {
+    match state.is_chance(color) {
+        true => state
+            .chance_outcomes(color)
+            .into_iter()
+            .filter(|&(_, weight)| weight > 0)
+            .collect(),
+        false => Vec::new(),
+    }
+}
+
+fn ceil_div(numerator: i128, denominator: i128) -> i128 {
+    -(-numerator).div_euclid(denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::{Add, Sub};
+
+    use super::{
+        super::{
+            super::traits::{ChanceStates, ChildStates, ScoreOfState, TerminalState},
+            search_state::{SearchContext, StopToken},
+        },
+        expectimax, star1, star2,
+    };
+
+    /// A random game tree with chance events of up to 4 weighted outcomes. The history is kept
+    /// as base 8 digits, moves push 1 to 3 and outcomes 4 to 7.
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    struct ChanceTree {
+        seed: u64,
+        history: u64,
+    }
+
+    impl ChanceTree {
+        fn hash(&self) -> u64 {
+            // SplitMix64
+            let mut z = self.seed ^ self.history.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+        fn last_digit(&self) -> u64 {
+            self.history % 8
+        }
+        fn push(self, digit: u64) -> Self {
+            Self {
+                history: self.history * 8 + digit,
+                ..self
+            }
+        }
+        fn pop(self) -> Self {
+            Self {
+                history: self.history / 8,
+                ..self
+            }
+        }
+    }
+
+    impl Add<u64> for ChanceTree {
+        type Output = Self;
+        fn add(self, digit: u64) -> Self {
+            self.push(digit)
+        }
+    }
+    impl Sub<u64> for ChanceTree {
+        type Output = Self;
+        fn sub(self, _: u64) -> Self {
+            self.pop()
+        }
+    }
+    impl ChildStates<u64> for ChanceTree {
+        fn child_states(&self, _color: bool) -> Vec<u64> {
+            (1..=1 + self.hash() % 3).collect()
+        }
+    }
+    impl ChanceStates<u64> for ChanceTree {
+        fn is_chance(&self, _color: bool) -> bool {
+            // Most moves and a few outcomes are followed by a chance event
+            match self.last_digit() {
+                0 => false,
+                1..=3 => !self.hash().is_multiple_of(4),
+                _ => self.hash().is_multiple_of(5) && self.pop().last_digit() < 4,
+            }
+        }
+        fn chance_outcomes(&self, _color: bool) -> Vec<(u64, u32)> {
+            let hash = self.hash();
+            (0..1 + (hash >> 8) % 4)
+                .map(|i| (4 + i, 1 + ((hash >> (16 + 4 * i)) % 6) as u32))
+                .collect()
+        }
+        /// Few different scores, so searches often hit the bounds and the edges of their window
+        fn score_bounds(&self) -> (isize, isize) {
+            (-5, 5)
+        }
+    }
+    impl ScoreOfState for ChanceTree {
+        fn score_of(&self) -> isize {
+            ((self.hash() >> 32) % 11) as isize - 5
+        }
+    }
+    impl TerminalState for ChanceTree {
+        fn is_terminal(&self) -> bool {
+            self.history != 0 && !self.is_chance(true) && self.hash().is_multiple_of(9)
+        }
+    }
+
+    #[test]
+    fn star1_and_star2_match_expectimax() {
+        for seed in 0..1000 {
+            let state = ChanceTree { seed, history: 0 };
+            for depth in 1..=4 {
+                for color in [true, false] {
+                    let context = &mut SearchContext::new();
+                    let expected = expectimax(&state, depth, color, context);
+                    let star1 = star1(&state, depth, color, context, isize::MIN + 1, isize::MAX);
+                    let star2 = star2(&state, depth, color, context, isize::MIN + 1, isize::MAX);
+                    assert_eq!(star1, expected, "star1, seed {seed}, depth {depth}");
+                    assert_eq!(star2, expected, "star2, seed {seed}, depth {depth}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_stopped_search_returns_none() {
+        let state = ChanceTree {
+            seed: 0,
+            history: 0,
+        };
+        let context = &mut SearchContext::new();
+        let expected = expectimax(&state, 6, true, context);
+        // Enough nodes for the search to look at its stop tokens
+        assert!(context.nodes() > 64);
+        let token = StopToken::new();
+        token.stop();
+        let stopped = context.watching(token, |context| expectimax(&state, 6, true, context));
+        assert_eq!(stopped, None);
+        // The token only stopped the search it was watched for
+        assert_eq!(expectimax(&state, 6, true, context), expected);
+    }
+}
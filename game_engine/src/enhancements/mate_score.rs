@@ -0,0 +1,47 @@
+/// Score of a won terminal state, for `score_of` of games that can end.
+/// It is far from `isize::MAX`, so negating it or adding plies to it never overflows.
+pub const WIN: isize = 1 << 48;
+/// Scores within this many plies of `WIN` are wins or losses in a number of plies
+const MAX_MATE_PLIES: isize = 1 << 16;
+
+/// Score of a win `plies` plies from now
+pub const fn win_in(plies: isize) -> isize {
+    WIN - plies
+}
+
+/// Score of a loss `plies` plies from now
+pub const fn loss_in(plies: isize) -> isize {
+    -WIN + plies
+}
+
+pub const fn is_mate_score(score: isize) -> bool {
+    score >= WIN - MAX_MATE_PLIES || score <= -WIN + MAX_MATE_PLIES
+}
+
+/// Plies until the game ends, positive if the score is a win and negative if it is a loss
+pub const fn mate_distance(score: isize) -> Option<isize> {
+    if score >= WIN - MAX_MATE_PLIES {
+        Some(WIN - score)
+    } else if score <= -WIN + MAX_MATE_PLIES {
+        Some(-(WIN + score))
+    } else {
+        None
+    }
+}
+
+/// Moves a mate score `plies` further away, other scores are kept.
+/// Searches use it to turn a terminal `score_of` into a score relative to the root.
+pub const fn add_plies(score: isize, plies: isize) -> isize {
+    if score >= WIN - MAX_MATE_PLIES {
+        score - plies
+    } else if score <= -WIN + MAX_MATE_PLIES {
+        score + plies
+    } else {
+        score
+    }
+}
+
+/// Moves a mate score `plies` closer, the inverse of `add_plies`
+pub const fn remove_plies(score: isize, plies: isize) -> isize {
+    add_plies(score, -plies)
+}
@@ -0,0 +1,304 @@
+extern crate rand;
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use super::super::traits::{ChildStates, ScoreOfState, TerminalState};
+
+/// How the moves of a playout are picked
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RolloutPolicy {
+    /// Uniformly random moves
+    Random,
+    /// Greedy on `score_of`, with a random move `epsilon` of the time
+    Heuristic { epsilon: f64 },
+}
+
+/// When to stop searching
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SearchBudget {
+    Time(Duration),
+    Playouts(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MctsConfig {
+    /// The `c` in UCT, sqrt(2) is the textbook value for results in [0, 1]
+    pub exploration: f64,
+    pub rollout: RolloutPolicy,
+    /// Playouts longer than this are scored as a draw
+    pub max_rollout_length: usize,
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            exploration: std::f64::consts::SQRT_2,
+            rollout: RolloutPolicy::Random,
+            max_rollout_length: 400,
+            seed: 42,
+        }
+    }
+}
+
+struct Node<M> {
+    /// The move that lead from the parent to this node
+    last_move: Option<M>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<M>,
+    /// The side to move in this node
+    color: bool,
+    visits: f64,
+    /// Results from the view of the side that played `last_move`
+    wins: f64,
+}
+
+/// A UCT search tree that can be kept alive between moves
+pub struct MonteCarloTree<M, T> {
+    root_state: Option<T>,
+    nodes: Vec<Node<M>>,
+    config: MctsConfig,
+    rng: StdRng,
+}
+
+impl<M, T> MonteCarloTree<M, T>
+where
+    M: Copy + PartialEq,
+    T: ChildStates<M> + TerminalState + ScoreOfState + Copy + PartialEq,
+{
+    pub fn new(config: MctsConfig) -> Self {
+        Self {
+            root_state: None,
+            nodes: Vec::new(),
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+        }
+    }
+
+    /// Number of playouts that went through the current root
+    pub fn playouts(&self) -> usize {
+        match self.nodes.first() {
+            Some(root) => root.visits as usize,
+            None => 0,
+        }
+    }
+
+    /// Runs playouts from `state` until the budget is spent and returns the most visited move
+    pub fn search(&mut self, state: &T, color: bool, budget: SearchBudget) -> M {
+        self.reuse_root(state, color);
+
+        let start = Instant::now();
+        let mut playouts = 0;
+        // The budget is checked after the playout, so the root always gets a child to return
+        loop {
+            self.playout(*state);
+            playouts += 1;
+            if self.nodes[0].untried.is_empty() && self.nodes[0].children.len() == 1 {
+                break;
+            }
+            match budget {
+                SearchBudget::Time(max_time) if start.elapsed() >= max_time => break,
+                SearchBudget::Playouts(max) if playouts >= max => break,
+                _ => (),
+            }
+        }
+        self.best_move()
+    }
+
+    fn best_move(&self) -> M {
+        let root = &self.nodes[0];
+        let best = root
+            .children
+            .iter()
+            .max_by(|&&a, &&b| self.nodes[a].visits.total_cmp(&self.nodes[b].visits))
+            .expect("No moves available from this state");
+        self.nodes[*best].last_move.unwrap()
+    }
+
+    // ! ======================= Tree reuse =======================
+    /// Keeps the subtree of `state` if it is the root, a child or a grandchild of the old root
+    fn reuse_root(&mut self, state: &T, color: bool) {
+        if let Some(root_state) = self.root_state {
+            if let Some(index) = self.find_node(root_state, 0, state, color, 2) {
+                self.reroot(index);
+                self.root_state = Some(*state);
+                return;
+            }
+        }
+        self.root_state = Some(*state);
+        self.nodes.clear();
+        self.nodes.push(Node {
+            last_move: None,
+            parent: None,
+            children: Vec::new(),
+            untried: state.child_states(color),
+            color,
+            visits: 0.0,
+            wins: 0.0,
+        });
+    }
+
+    fn find_node(
+        &self,
+        node_state: T,
+        index: usize,
+        state: &T,
+        color: bool,
+        max_depth: usize,
+    ) -> Option<usize> {
+        if node_state == *state && self.nodes[index].color == color {
+            return Some(index);
+        }
+        if max_depth == 0 {
+            return None;
+        }
+        for &child in &self.nodes[index].children {
+            let child_state = node_state + self.nodes[child].last_move.unwrap();
+            if let Some(found) = self.find_node(child_state, child, state, color, max_depth - 1) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    fn reroot(&mut self, index: usize) {
+        if index == 0 {
+            return;
+        }
+        let mut old_nodes: Vec<Option<Node<M>>> = self.nodes.drain(..).map(Some).collect();
+        let mut queue = vec![(index, None)];
+        while let Some((old_index, parent)) = queue.pop() {
+            let mut node = old_nodes[old_index].take().unwrap();
+            let new_index = self.nodes.len();
+            node.parent = parent;
+            if parent.is_none() {
+                node.last_move = None;
+            }
+            for &child in &node.children {
+                queue.push((child, Some(new_index)));
+            }
+            node.children.clear();
+            if let Some(parent) = parent {
+                self.nodes[parent].children.push(new_index);
+            }
+            self.nodes.push(node);
+        }
+    }
+
+    // ! ======================= UCT steps =======================
+    fn playout(&mut self, root_state: T) {
+        let (mut index, mut state) = self.select(root_state);
+
+        if !self.nodes[index].untried.is_empty() && !state.is_terminal() {
+            let (child, child_state) = self.expand(index, state);
+            index = child;
+            state = child_state;
+        }
+
+        let result = self.rollout(state, self.nodes[index].color);
+        self.backpropagate(index, result);
+    }
+
+    fn select(&self, mut state: T) -> (usize, T) {
+        let mut index = 0;
+        while self.nodes[index].untried.is_empty() && !self.nodes[index].children.is_empty() {
+            let parent_visits = self.nodes[index].visits;
+            let best = self.nodes[index]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| {
+                    self.uct(a, parent_visits)
+                        .total_cmp(&self.uct(b, parent_visits))
+                })
+                .unwrap();
+            index = *best;
+            state = state + self.nodes[index].last_move.unwrap();
+        }
+        (index, state)
+    }
+
+    fn uct(&self, index: usize, parent_visits: f64) -> f64 {
+        let node = &self.nodes[index];
+        if node.visits == 0.0 {
+            return f64::INFINITY;
+        }
+        node.wins / node.visits
+            + self.config.exploration * (parent_visits.ln() / node.visits).sqrt()
+    }
+
+    fn expand(&mut self, index: usize, state: T) -> (usize, T) {
+        let untried = &mut self.nodes[index].untried;
+        let pick = self.rng.gen_range(0..untried.len());
+        let new_move = untried.swap_remove(pick);
+        let color = !self.nodes[index].color;
+        let child_state = state + new_move;
+        let untried = match child_state.is_terminal() {
+            true => Vec::new(),
+            false => child_state.child_states(color),
+        };
+
+        let child = self.nodes.len();
+        self.nodes.push(Node {
+            last_move: Some(new_move),
+            parent: Some(index),
+            children: Vec::new(),
+            untried,
+            color,
+            visits: 0.0,
+            wins: 0.0,
+        });
+        self.nodes[index].children.push(child);
+        (child, child_state)
+    }
+
+    /// Plays the game out and returns the result for `true`: 1 win, 0 loss, 0.5 draw
+    fn rollout(&mut self, mut state: T, mut color: bool) -> f64 {
+        for _ in 0..self.config.max_rollout_length {
+            if state.is_terminal() {
+                break;
+            }
+            let moves = state.child_states(color);
+            let next_move = match self.config.rollout {
+                RolloutPolicy::Random => moves.choose(&mut self.rng),
+                RolloutPolicy::Heuristic { epsilon } => match self.rng.gen_bool(epsilon) {
+                    true => moves.choose(&mut self.rng),
+                    false => moves.iter().max_by_key(|&&next_move| match color {
+                        true => (state + next_move).score_of(),
+                        false => -(state + next_move).score_of(),
+                    }),
+                },
+            };
+            match next_move {
+                Some(&next_move) => state = state + next_move,
+                None => return 0.5,
+            }
+            color = !color;
+        }
+        if !state.is_terminal() {
+            return 0.5;
+        }
+        match state.score_of() {
+            x if x > 0 => 1.0,
+            x if x < 0 => 0.0,
+            _ => 0.5,
+        }
+    }
+
+    fn backpropagate(&mut self, mut index: usize, result: f64) {
+        loop {
+            let node = &mut self.nodes[index];
+            node.visits += 1.0;
+            node.wins += match node.color {
+                // `true` is to move, so `false` made the move into this node
+                true => 1.0 - result,
+                false => result,
+            };
+            match node.parent {
+                Some(parent) => index = parent,
+                None => break,
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// History scores are halved once one of them passes this
+const HISTORY_LIMIT: isize = 1 << 24;
+
+/// Killer moves per ply, a history table and a countermove table used to order the moves of a node.
+/// A search tells it which moves it plays with `push`/`pop` and which move caused a cutoff.
+/// The move finders create one for every root search, and one per thread for Lazy SMP.
+pub struct MoveOrdering<M> {
+    pub use_killers: bool,
+    pub use_history: bool,
+    pub use_countermoves: bool,
+    /// The moves from the root to the current node, `None` for a pass
+    line: Vec<Option<M>>,
+    killers: Vec<[Option<M>; 2]>,
+    history: BTreeMap<M, isize>,
+    countermoves: BTreeMap<M, M>,
+}
+
+impl<M> Default for MoveOrdering<M> {
+    fn default() -> Self {
+        Self {
+            use_killers: true,
+            use_history: true,
+            use_countermoves: true,
+            line: Vec::new(),
+            killers: Vec::new(),
+            history: BTreeMap::new(),
+            countermoves: BTreeMap::new(),
+        }
+    }
+}
+
+impl<M> MoveOrdering<M>
+where
+    M: Copy + Ord,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Only puts the TT move first, which is what the plain searches do
+    pub fn disabled() -> Self {
+        Self {
+            use_killers: false,
+            use_history: false,
+            use_countermoves: false,
+            ..Self::default()
+        }
+    }
+    fn is_enabled(&self) -> bool {
+        self.use_killers || self.use_history || self.use_countermoves
+    }
+
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.killers.clear();
+        self.history.clear();
+        self.countermoves.clear();
+    }
+
+    /// Distance from the node the search started in
+    pub fn ply(&self) -> usize {
+        self.line.len()
+    }
+    /// Call before searching the child reached by `next_move`
+    pub fn push(&mut self, next_move: M) {
+        self.line.push(Some(next_move));
+    }
+    /// Call before searching the state after a null move, it counts as a ply
+    pub fn push_pass(&mut self) {
+        self.line.push(None);
+    }
+    /// Call after the child search returned
+    pub fn pop(&mut self) {
+        self.line.pop();
+    }
+
+    /// TT move first, then the killers of this ply, the countermove and the rest by history.
+    /// Moves that score the same keep their generated order.
+    pub fn order(&self, moves: &mut [M], tt_move: Option<M>) {
+        if !self.is_enabled() {
+            if let Some(index) = tt_move.and_then(|tt| moves.iter().position(|&m| m == tt)) {
+                moves[..=index].rotate_right(1);
+            }
+            return;
+        }
+        let killers = match self.use_killers {
+            true => self.killers.get(self.ply()).copied().unwrap_or_default(),
+            false => [None, None],
+        };
+        let countermove = match self.use_countermoves {
+            true => self
+                .line
+                .last()
+                .copied()
+                .flatten()
+                .and_then(|previous| self.countermoves.get(&previous))
+                .copied(),
+            false => None,
+        };
+        moves.sort_by_cached_key(|&next_move| {
+            Reverse(if Some(next_move) == tt_move {
+                isize::MAX
+            } else if Some(next_move) == killers[0] {
+                isize::MAX - 1
+            } else if Some(next_move) == killers[1] {
+                isize::MAX - 2
+            } else if Some(next_move) == countermove {
+                isize::MAX - 3
+            } else if self.use_history {
+                self.history.get(&next_move).copied().unwrap_or(0)
+            } else {
+                0
+            })
+        });
+    }
+
+    /// Call when `next_move` caused a beta cutoff with `depth` plies left
+    pub fn cutoff(&mut self, next_move: M, depth: isize) {
+        if self.use_killers {
+            let ply = self.ply();
+            if self.killers.len() <= ply {
+                self.killers.resize(ply + 1, [None, None]);
+            }
+            let killers = &mut self.killers[ply];
+            if killers[0] != Some(next_move) {
+                killers[1] = killers[0];
+                killers[0] = Some(next_move);
+            }
+        }
+        if self.use_history {
+            let score = self.history.entry(next_move).or_insert(0);
+            *score += depth * depth;
+            if *score > HISTORY_LIMIT {
+                for score in self.history.values_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+        if self.use_countermoves {
+            if let Some(&Some(previous)) = self.line.last() {
+                self.countermoves.insert(previous, next_move);
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use super::{
+    super::{
+        function_types::WindowSearchFunctionWithTable,
+        traits::{ChildStates, ScoreOfState, StateHash, TerminalState},
+    },
+    search_state::{SearchContext, StopToken},
+    transposition_table::TranspositionTable,
+};
+
+/// MTD(f), narrows the bounds on the value with zero window searches until they meet.
+/// Relies on the table to keep the re-searches cheap. Scores are from the view of `true`, and so
+/// must be those of `search_function`: `alpha_beta_with_table` or `nega_with_table_for_true`,
+/// not `nega_with_table`.
+pub fn mtdf<M, T>(
+    state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    first_guess: isize,
+    search_function: WindowSearchFunctionWithTable<M, T>,
+) -> Option<isize>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + StateHash,
+{
+    let mut guess = first_guess;
+    let mut lower_bound = isize::MIN + 1;
+    let mut upper_bound = isize::MAX;
+    while lower_bound < upper_bound {
+        let beta = match guess == lower_bound {
+            true => guess + 1,
+            false => guess,
+        };
+        guess = search_function(state, depth, color, table, context, beta - 1, beta)?;
+        if guess < beta {
+            upper_bound = guess;
+        } else {
+            lower_bound = guess;
+        }
+    }
+    Some(guess)
+}
+
+/// Depth limited ID, every depth is an MTD(f) search seeded with the score of the previous one
+pub fn iterative_deepening_mtdf_tt<M, T>(
+    state: &T,
+    max_depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    search_function: WindowSearchFunctionWithTable<M, T>,
+) -> Option<isize>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + StateHash,
+{
+    let mut guess = 0;
+    for depth in 1..=max_depth {
+        guess = mtdf(state, depth, color, table, context, guess, search_function)?;
+        context.record_depth(depth);
+    }
+    Some(guess)
+}
+
+/// Timed ID with MTD(f), returns the score of the last completed iteration or `None` if not even
+/// the first one finished
+pub fn iterative_deepening_mtdf_t_tt<M, T>(
+    state: &T,
+    color: bool,
+    max_time: Duration,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    search_function: WindowSearchFunctionWithTable<M, T>,
+) -> Option<isize>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + StateHash,
+{
+    let mut score = None;
+    let mut guess = 0;
+    let mut depth = 1;
+    let start = Instant::now();
+    context.watching(StopToken::with_deadline(start + max_time), |context| {
+        while start.elapsed() < max_time {
+            let Some(iteration_score) =
+                mtdf(state, depth, color, table, context, guess, search_function)
+            else {
+                break;
+            };
+            guess = iteration_score;
+            score = Some(guess);
+            context.record_depth(depth);
+            depth += 1;
+        }
+    });
+    score
+}
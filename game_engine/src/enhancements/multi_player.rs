@@ -0,0 +1,112 @@
+use std::cmp::{max, min};
+
+use super::{
+    super::{
+        player::Player,
+        traits::{MultiPlayerStates, ScoresOfState, TerminalState},
+    },
+    search_state::SearchContext,
+};
+
+/// Every player picks the move that is best for their own score. Returns the scores at the end
+/// of the line everyone plays, ties go to the first move.
+pub fn max_n<M, T>(
+    &state: &T,
+    depth: isize,
+    player: Player,
+    context: &mut SearchContext,
+) -> Option<Vec<isize>>
+where
+    M: Copy,
+    T: MultiPlayerStates<M> + ScoresOfState + TerminalState + Copy,
+{
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
+    if 0 == depth || state.is_terminal() {
+        return Some(state.scores_of());
+    }
+
+    let next_player = state.next_player(player);
+    let mut best: Option<Vec<isize>> = None;
+    for new_move in state.player_moves(player) {
+        let scores = max_n(&(state + new_move), depth - 1, next_player, context)?;
+        if best
+            .as_ref()
+            .is_none_or(|best| scores[player.0] > best[player.0])
+        {
+            best = Some(scores);
+        }
+    }
+    // A player without moves ends the line like a terminal state
+    Some(best.unwrap_or_else(|| state.scores_of()))
+}
+
+/// Alpha beta on the score of `root`, where every other player is assumed to play against it
+pub fn paranoid<M, T>(
+    &state: &T,
+    depth: isize,
+    player: Player,
+    root: Player,
+    context: &mut SearchContext,
+    mut alpha: isize,
+    mut beta: isize,
+) -> Option<isize>
+where
+    M: Copy,
+    T: MultiPlayerStates<M> + ScoresOfState + TerminalState + Copy,
+{
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
+    if 0 == depth || state.is_terminal() {
+        return Some(state.scores_of()[root.0]);
+    }
+    let moves = state.player_moves(player);
+    if moves.is_empty() {
+        return Some(state.scores_of()[root.0]);
+    }
+
+    let next_player = state.next_player(player);
+    let mut score: isize;
+    if player == root {
+        score = isize::MIN + 1;
+        for new_move in moves {
+            let value = paranoid(
+                &(state + new_move),
+                depth - 1,
+                next_player,
+                root,
+                context,
+                alpha,
+                beta,
+            )?;
+            score = max(score, value);
+            alpha = max(alpha, score);
+            if score >= beta {
+                break;
+            }
+        }
+    } else {
+        score = isize::MAX;
+        for new_move in moves {
+            let value = paranoid(
+                &(state + new_move),
+                depth - 1,
+                next_player,
+                root,
+                context,
+                alpha,
+                beta,
+            )?;
+            score = min(score, value);
+            beta = min(beta, score);
+            if score <= alpha {
+                break;
+            }
+        }
+    }
+    Some(score)
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use rand::{seq::SliceRandom, Rng};
+
+use super::{
+    super::{
+        function_types::DepthSearchWithTable,
+        runner::GameOutcome,
+        traits::{ByteEncoding, ChildStates, StateHash, TerminalState},
+    },
+    mate_score::add_plies,
+    move_ordering::MoveOrdering,
+    search_state::SearchContext,
+    table_persistence::{invalid, read_array, read_header, write_header},
+    transposition_table::TranspositionTable,
+};
+
+const MAGIC: &[u8; 4] = b"IGOB";
+const VERSION: u8 = 1;
+
+/// Weighted moves for known states, keyed by `StateHash::hash` and `StateHash::verification`
+/// of the state with the side to move
+#[derive(Clone, Debug)]
+pub struct OpeningBook<M> {
+    entries: HashMap<(isize, u32), Vec<(M, u32)>>,
+}
+
+impl<M> Default for OpeningBook<M> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<M> OpeningBook<M>
+where
+    M: Copy + PartialEq,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The number of states in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Adds `weight` to `book_move` in `state`
+    pub fn add<T: StateHash>(&mut self, state: &T, color: bool, book_move: M, weight: u32) {
+        if weight == 0 {
+            return;
+        }
+        let moves = self
+            .entries
+            .entry((state.hash(color), state.verification(color)))
+            .or_default();
+        match moves.iter_mut().find(|(known, _)| *known == book_move) {
+            Some((_, known_weight)) => *known_weight = known_weight.saturating_add(weight),
+            None => moves.push((book_move, weight)),
+        }
+    }
+    /// The moves of `state` with their weights, empty when it isn't in the book
+    pub fn moves<T: StateHash>(&self, state: &T, color: bool) -> &[(M, u32)] {
+        self.entries
+            .get(&(state.hash(color), state.verification(color)))
+            .map_or(&[], |moves| moves.as_slice())
+    }
+    /// A legal book move of `state`, picked with a chance in proportion to its weight
+    pub fn choose<T, R>(&self, state: &T, color: bool, rng: &mut R) -> Option<M>
+    where
+        T: ChildStates<M> + StateHash,
+        R: Rng,
+    {
+        let moves = self.moves(state, color);
+        if moves.is_empty() {
+            return None;
+        }
+        // A colliding hash could give moves of another state
+        let legal = state.child_states(color);
+        let moves: Vec<_> = moves
+            .iter()
+            .filter(|(book_move, _)| legal.contains(book_move))
+            .collect();
+        moves
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .ok()
+            .map(|(book_move, _)| *book_move)
+    }
+
+    // ! ======================= Building =======================
+    /// Searches every move `depth` deep in the states `plies` from `state`. Moves at most `margin`
+    /// worse than the best one go in the book and get followed, the closer the heavier they weigh.
+    /// States that are already in the book are skipped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_analysis<T, S>(
+        &mut self,
+        &state: &T,
+        color: bool,
+        plies: usize,
+        depth: isize,
+        margin: isize,
+        table: &TranspositionTable<M>,
+        search_function: &S,
+    ) where
+        T: ChildStates<M> + StateHash + TerminalState + Copy,
+        S: DepthSearchWithTable<M, T>,
+    {
+        if plies == 0 || state.is_terminal() || !self.moves(&state, color).is_empty() {
+            return;
+        }
+        // Scores from the view of the mover
+        let mut ordering = MoveOrdering::default();
+        let mut context = SearchContext::new();
+        let scored: Vec<(M, isize)> = state
+            .child_states(color)
+            .into_iter()
+            .filter_map(|child| {
+                let score = search_function(
+                    &(state + child),
+                    depth - 1,
+                    !color,
+                    table,
+                    &mut ordering,
+                    &mut context,
+                )?;
+                let score = add_plies(score, 1);
+                Some(match color {
+                    true => (child, score),
+                    false => (child, -score),
+                })
+            })
+            .collect();
+        let Some(best) = scored.iter().map(|(_, score)| *score).max() else {
+            return;
+        };
+        for (child, score) in scored {
+            let behind = best.saturating_sub(score);
+            if behind > margin {
+                continue;
+            }
+            self.add(&state, color, child, (margin - behind) as u32 + 1);
+            self.add_analysis(
+                &(state + child),
+                !color,
+                plies - 1,
+                depth,
+                margin,
+                table,
+                search_function,
+            );
+        }
+    }
+    /// Adds the first `plies` moves of a game that started in `start` with `true` to move.
+    /// Moves of the winner weigh 2 and moves of a draw 1, moves of the loser aren't added.
+    pub fn add_game<T>(&mut self, start: &T, outcome: &GameOutcome<M>, plies: usize)
+    where
+        T: ChildStates<M> + StateHash + Copy,
+    {
+        let mut state = *start;
+        for record in outcome.moves.iter().take(plies) {
+            let weight = match outcome.winner() {
+                Some(winner) if winner == record.color => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+            self.add(&state, record.color, record.played, weight);
+            state = state + record.played;
+        }
+    }
+}
+
+/// Saving and loading, with the same header as a saved transposition table.
+///
+/// Layout: magic, version, game name, seed, state count, then per state key (i64),
+/// verification (u32), move count (u32) and per move the move and its weight (u32).
+impl<M> OpeningBook<M>
+where
+    M: Copy + PartialEq + ByteEncoding,
+{
+    pub fn save(&self, path: impl AsRef<Path>, game: &str, seed: u64) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_header(&mut writer, MAGIC, VERSION, game, seed)?;
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (&(key, check), moves) in &self.entries {
+            writer.write_all(&(key as i64).to_le_bytes())?;
+            writer.write_all(&check.to_le_bytes())?;
+            writer.write_all(&(moves.len() as u32).to_le_bytes())?;
+            for (book_move, weight) in moves {
+                writer.write_all(&book_move.encode())?;
+                writer.write_all(&weight.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>, game: &str, seed: u64) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        read_header(&mut reader, MAGIC, VERSION, game, seed, "opening book")?;
+        let count = u64::from_le_bytes(read_array(&mut reader)?) as usize;
+
+        let mut book = Self::default();
+        let mut move_bytes = vec![0; M::ENCODED_SIZE];
+        for _ in 0..count {
+            let key = i64::from_le_bytes(read_array(&mut reader)?) as isize;
+            let check = u32::from_le_bytes(read_array(&mut reader)?);
+            let move_count = u32::from_le_bytes(read_array(&mut reader)?) as usize;
+            // Not allocated up front, the count could be anything in a broken file
+            let mut moves = Vec::new();
+            for _ in 0..move_count {
+                reader.read_exact(&mut move_bytes)?;
+                let book_move = M::decode(&move_bytes)
+                    .ok_or_else(|| invalid(String::from("Invalid move in book")))?;
+                let weight = u32::from_le_bytes(read_array(&mut reader)?);
+                moves.push((book_move, weight));
+            }
+            book.entries.insert((key, check), moves);
+        }
+        Ok(book)
+    }
+}
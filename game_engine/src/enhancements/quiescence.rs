@@ -0,0 +1,70 @@
+use std::cmp::max;
+
+use super::{
+    super::traits::{ChildStates, NoisyMoves, ScoreOfState, TerminalState},
+    mate_score::add_plies,
+    search_state::SearchContext,
+};
+
+/// The plain leaf evaluation, `score_of` from the view of `color` with mate scores counted from
+/// the root
+pub fn static_score<T: ScoreOfState>(
+    state: &T,
+    color: bool,
+    ply: usize,
+    _alpha: isize,
+    _beta: isize,
+) -> isize {
+    let score = add_plies(state.score_of(), ply as isize);
+    match color {
+        true => score,
+        false => -score,
+    }
+}
+
+/// `static_score` in the form of a `LeafFunction`, it doesn't search so it is never stopped
+pub fn static_leaf<T: ScoreOfState>(
+    state: &T,
+    color: bool,
+    ply: usize,
+    _context: &mut SearchContext,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize> {
+    Some(static_score(state, color, ply, alpha, beta))
+}
+
+/// Searches only the noisy moves until the position is quiet, from the view of `color`.
+/// The side to move can always stand pat on the static score instead of making a noisy move.
+pub fn quiescence<M, T>(
+    &state: &T,
+    color: bool,
+    ply: usize,
+    context: &mut SearchContext,
+    mut alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + NoisyMoves<M> + Copy,
+{
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
+    let stand_pat = static_score(&state, color, ply, alpha, beta);
+    if state.is_terminal() || stand_pat >= beta {
+        return Some(stand_pat);
+    }
+    alpha = max(alpha, stand_pat);
+
+    let mut score = stand_pat;
+    for new_move in state.noisy_moves(color) {
+        let value = -quiescence(&(state + new_move), !color, ply + 1, context, -beta, -alpha)?;
+        score = max(score, value);
+        alpha = max(alpha, score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    Some(score)
+}
@@ -0,0 +1,11 @@
+/// Which entry of a full bucket a new entry is allowed to overwrite.
+/// Entries from an older search are always overwritten first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplacementPolicy {
+    /// The new entry always gets stored
+    AlwaysReplace,
+    /// The shallowest entry is replaced, but never by a shallower one
+    DepthPreferred,
+    /// Half the bucket is depth preferred, the other half always replaced
+    TwoTier,
+}
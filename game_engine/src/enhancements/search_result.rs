@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+/// Everything a move finder knows after a search
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult<M> {
+    pub best_move: M,
+    /// Score of `best_move`, positive is good for `true`
+    pub score: isize,
+    /// The deepest search depth that was completed for every root move
+    pub depth: isize,
+    pub nodes: usize,
+    pub elapsed: Duration,
+    /// The expected line of play, starting with `best_move`
+    pub pv: Vec<M>,
+}
+
+impl<M: Copy> SearchResult<M> {
+    /// Result for a position with a single legal move, nothing was searched
+    pub fn forced(best_move: M, score: isize, elapsed: Duration) -> Self {
+        Self {
+            best_move,
+            score,
+            depth: 0,
+            nodes: 0,
+            elapsed,
+            pv: vec![best_move],
+        }
+    }
+}
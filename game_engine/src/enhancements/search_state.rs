@@ -0,0 +1,121 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+/// How many nodes are searched between two looks at the stop tokens
+const STOP_CHECK_INTERVAL: usize = 64;
+
+/// The stop tokens and the bookkeeping of one search, every search function takes it.
+/// A search that stops returns `None`, so a partial score can't be mistaken for a result.
+#[derive(Debug, Default)]
+pub struct SearchContext {
+    tokens: Vec<StopToken>,
+    nodes: usize,
+    depth: isize,
+    stopped: bool,
+}
+
+impl SearchContext {
+    /// A search that only stops when it is done
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// A search that stops when `token` fires
+    pub fn with_stop_token(token: StopToken) -> Self {
+        Self {
+            tokens: vec![token],
+            ..Self::default()
+        }
+    }
+
+    /// Called once by every search function for each node it enters
+    pub fn count_node(&mut self) {
+        self.nodes += 1;
+    }
+    /// Adds nodes that were searched with another context, like the one of another thread
+    pub fn add_nodes(&mut self, count: usize) {
+        self.nodes += count;
+    }
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Called by iterative deepening after every finished iteration
+    pub fn record_depth(&mut self, depth: isize) {
+        self.depth = self.depth.max(depth);
+    }
+    /// The deepest iteration finished with this context
+    pub fn depth(&self) -> isize {
+        self.depth
+    }
+
+    /// Runs `search` while also watching `token`, e.g. the deadline of a timed search inside a
+    /// longer one. Tokens nest, a search stops when any of them fires.
+    pub fn watching<R>(&mut self, token: StopToken, search: impl FnOnce(&mut Self) -> R) -> R {
+        self.tokens.push(token);
+        let result = search(self);
+        self.tokens.pop();
+        self.check_stop();
+        result
+    }
+
+    /// Called by the searches on every node, once this returns true the search should unwind
+    pub fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if !self.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            return false;
+        }
+        self.check_stop()
+    }
+    /// Looks at the stop tokens right away, for use outside of the node loop
+    pub fn check_stop(&mut self) -> bool {
+        self.stopped = self.tokens.iter().any(StopToken::is_stopped);
+        self.stopped
+    }
+}
+
+// ! ========================== Cancellation ==========================
+/// A shared flag and an optional deadline, a search aborts as soon as either one fires.
+/// Clones share the flag, so another thread can stop a running search.
+#[derive(Clone, Debug, Default)]
+pub struct StopToken {
+    flag: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl StopToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            flag: Arc::new(AtomicBool::new(false)),
+            deadline: Some(deadline),
+        }
+    }
+    pub fn stop(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+            || matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+    /// Stops the token when the guard is dropped, also when the thread unwinds from a panic
+    pub fn stop_on_drop(&self) -> StopOnDrop {
+        StopOnDrop(self.clone())
+    }
+}
+
+pub struct StopOnDrop(StopToken);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
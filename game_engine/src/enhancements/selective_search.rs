@@ -0,0 +1,241 @@
+use std::cmp::{max, min};
+
+use super::{
+    super::traits::{ChildStates, NullMove, ScoreOfState, StateHash, TerminalState},
+    move_ordering::MoveOrdering,
+    quiescence::static_score,
+    search_state::SearchContext,
+    transposition_state_type::TranspositionStateType,
+    transposition_table::TranspositionTable,
+};
+
+/// Which forward pruning the search may use, everything is off by default
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SelectiveSearch {
+    pub null_move: bool,
+    /// Extra depth taken off the null move search, on top of the ply of the pass itself
+    pub null_move_reduction: isize,
+    pub late_move_reductions: bool,
+    /// Moves searched at full depth before the rest get reduced
+    pub lmr_full_depth_moves: usize,
+    /// Nodes with less depth left are never reduced
+    pub lmr_min_depth: isize,
+    pub lmr_reduction: isize,
+    pub futility_pruning: bool,
+    /// A frontier node whose static score is this far outside the window isn't searched
+    pub futility_margin: isize,
+}
+
+impl Default for SelectiveSearch {
+    fn default() -> Self {
+        Self {
+            null_move: false,
+            null_move_reduction: 2,
+            late_move_reductions: false,
+            lmr_full_depth_moves: 3,
+            lmr_min_depth: 3,
+            lmr_reduction: 1,
+            futility_pruning: false,
+            futility_margin: 50,
+        }
+    }
+}
+
+impl SelectiveSearch {
+    /// Every feature on, with the default parameters
+    pub fn all() -> Self {
+        Self {
+            null_move: true,
+            late_move_reductions: true,
+            futility_pruning: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// `nega_with_table_ordered` with the pruning of `options`
+#[allow(clippy::too_many_arguments)]
+pub fn nega_with_table_selective<M, T>(
+    state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    options: &SelectiveSearch,
+    alpha: isize,
+    beta: isize,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash + NullMove,
+{
+    selective(
+        state, depth, color, table, ordering, context, options, alpha, beta, true,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn selective<M, T>(
+    &state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+    options: &SelectiveSearch,
+    mut alpha: isize,
+    mut beta: isize,
+    allow_null: bool,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash + NullMove,
+{
+    context.count_node();
+    if context.should_stop() {
+        return None;
+    }
+    let original_alpha = alpha;
+
+    // ! =================== Check TT ==================
+    let (stored_value, stored_depth, state_type, stored_best_move) =
+        table.probe(&state, color, ordering.ply());
+    if state_type != TranspositionStateType::Unknown && stored_depth >= depth {
+        match state_type {
+            TranspositionStateType::Exact => {
+                table.record_cutoff(state_type);
+                return Some(stored_value);
+            }
+            TranspositionStateType::LowerBound => alpha = max(alpha, stored_value),
+            TranspositionStateType::UpperBound => beta = min(beta, stored_value),
+            _ => panic!(),
+        }
+        if alpha >= beta {
+            table.record_cutoff(state_type);
+            return Some(stored_value);
+        }
+    }
+
+    let static_score = static_score(&state, color, ordering.ply(), alpha, beta);
+    if 0 == depth || state.is_terminal() {
+        return Some(static_score);
+    }
+    let is_root = ordering.ply() == 0;
+
+    // ! =================== Futility pruning ==================
+    if options.futility_pruning
+        && depth == 1
+        && !is_root
+        && (static_score.saturating_add(options.futility_margin) <= alpha
+            || static_score.saturating_sub(options.futility_margin) >= beta)
+    {
+        return Some(static_score);
+    }
+
+    // ! =================== Null move pruning ==================
+    // If passing still fails high, a real move will too. Never twice in a row and never at the root.
+    if options.null_move
+        && allow_null
+        && !is_root
+        && depth > options.null_move_reduction
+        && beta < isize::MAX
+        && state.can_pass(color)
+    {
+        ordering.push_pass();
+        let null_value = selective(
+            &state,
+            depth - 1 - options.null_move_reduction,
+            !color,
+            table,
+            ordering,
+            context,
+            options,
+            -beta,
+            -beta + 1,
+            false,
+        );
+        ordering.pop();
+        if -null_value? >= beta {
+            return Some(beta);
+        }
+    }
+
+    let mut ordered_children = state.child_states(color);
+    let tt_move = (state_type != TranspositionStateType::Unknown).then_some(stored_best_move);
+    ordering.order(&mut ordered_children, tt_move);
+
+    let mut value = isize::MIN + 1;
+    let mut best_move = M::default();
+    for (index, child) in ordered_children.into_iter().enumerate() {
+        let child_state = state + child;
+        ordering.push(child);
+
+        // ! =================== Late move reductions ==================
+        let reduce = options.late_move_reductions
+            && index >= options.lmr_full_depth_moves
+            && depth >= options.lmr_min_depth
+            && Some(child) != tt_move;
+        let mut child_value = Some(alpha.saturating_add(1));
+        if reduce {
+            child_value = selective(
+                &child_state,
+                // A reduction past the horizon makes the child a leaf
+                (depth - 1 - options.lmr_reduction).max(0),
+                !color,
+                table,
+                ordering,
+                context,
+                options,
+                -alpha - 1,
+                -alpha,
+                true,
+            )
+            .map(|v| -v);
+        }
+        // Not reduced, or the reduced search beat alpha, search at full depth
+        if matches!(child_value, Some(v) if v > alpha) {
+            child_value = selective(
+                &child_state,
+                depth - 1,
+                !color,
+                table,
+                ordering,
+                context,
+                options,
+                -beta,
+                -alpha,
+                true,
+            )
+            .map(|v| -v);
+        }
+        ordering.pop();
+        let child_value = child_value?;
+        if value < child_value {
+            best_move = child;
+        }
+        value = max(value, child_value);
+        alpha = max(alpha, value);
+        if alpha >= beta {
+            ordering.cutoff(child, depth);
+            break;
+        }
+    }
+    // ! ==================== Store in TT ===================
+    let flag: TranspositionStateType;
+    if value <= original_alpha {
+        flag = TranspositionStateType::UpperBound;
+    } else if value >= beta {
+        flag = TranspositionStateType::LowerBound;
+    } else {
+        flag = TranspositionStateType::Exact;
+    }
+    table.store(
+        &state,
+        color,
+        ordering.ply(),
+        (value, depth, flag, best_move),
+    );
+
+    Some(value)
+}
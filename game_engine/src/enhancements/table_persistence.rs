@@ -0,0 +1,154 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use super::{
+    super::traits::ByteEncoding, transposition_state_type::TranspositionStateType,
+    transposition_table::TranspositionTable,
+};
+
+const MAGIC: &[u8; 4] = b"IGTT";
+const VERSION: u8 = 1;
+
+pub(super) fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+pub(super) fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Magic, version, game name and seed, shared by every file written for a game
+pub(super) fn write_header(
+    writer: &mut impl Write,
+    magic: &[u8; 4],
+    version: u8,
+    game: &str,
+    seed: u64,
+) -> io::Result<()> {
+    writer.write_all(magic)?;
+    writer.write_all(&[version])?;
+    writer.write_all(&(game.len() as u32).to_le_bytes())?;
+    writer.write_all(game.as_bytes())?;
+    writer.write_all(&seed.to_le_bytes())
+}
+
+/// Checks a header written by `write_header`, `kind` names the file in the errors
+pub(super) fn read_header(
+    reader: &mut impl Read,
+    magic: &[u8; 4],
+    version: u8,
+    game: &str,
+    seed: u64,
+    kind: &str,
+) -> io::Result<()> {
+    if &read_array::<4>(reader)? != magic {
+        return Err(invalid(format!("Not a {} file", kind)));
+    }
+    let [file_version] = read_array::<1>(reader)?;
+    if file_version != version {
+        return Err(invalid(format!(
+            "Unsupported {} version {}",
+            kind, file_version
+        )));
+    }
+    // Read through `take`, so a broken length can't allocate more than the file holds
+    let name_length = u32::from_le_bytes(read_array(reader)?) as u64;
+    let mut name = Vec::new();
+    reader.take(name_length).read_to_end(&mut name)?;
+    if name.len() as u64 != name_length {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    if name != game.as_bytes() {
+        return Err(invalid(format!(
+            "The {} was made for {}, not for {}",
+            kind,
+            String::from_utf8_lossy(&name),
+            game
+        )));
+    }
+    let file_seed = u64::from_le_bytes(read_array(reader)?);
+    if file_seed != seed {
+        return Err(invalid(format!(
+            "The {} was made with hash seed {}, not with {}",
+            kind, file_seed, seed
+        )));
+    }
+    Ok(())
+}
+
+const fn state_type_to_byte(state_type: TranspositionStateType) -> u8 {
+    state_type as u8
+}
+
+fn state_type_from_byte(byte: u8) -> io::Result<TranspositionStateType> {
+    match byte {
+        0 => Ok(TranspositionStateType::Exact),
+        1 => Ok(TranspositionStateType::LowerBound),
+        2 => Ok(TranspositionStateType::UpperBound),
+        x => Err(invalid(format!("Unknown entry type {}", x))),
+    }
+}
+
+/// Saving and loading, the file records the game and the seed of its hash field
+/// so a table can't be loaded into a game that hashes states differently.
+///
+/// Layout: magic, version, game name, seed, entry count, then per entry
+/// key (i64), verification (u32), value (i64), depth (i32), type (u8) and the move.
+impl<M> TranspositionTable<M>
+where
+    M: Default + Copy + PartialEq + ByteEncoding,
+{
+    pub fn save(&self, path: impl AsRef<Path>, game: &str, seed: u64) -> io::Result<()> {
+        let entries = self.entries();
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        write_header(&mut writer, MAGIC, VERSION, game, seed)?;
+        writer.write_all(&(entries.len() as u64).to_le_bytes())?;
+
+        for (key, check, (value, depth, state_type, best_move)) in entries {
+            writer.write_all(&(key as i64).to_le_bytes())?;
+            writer.write_all(&check.to_le_bytes())?;
+            writer.write_all(&(value as i64).to_le_bytes())?;
+            writer.write_all(&(depth as i32).to_le_bytes())?;
+            writer.write_all(&[state_type_to_byte(state_type)])?;
+            writer.write_all(&best_move.encode())?;
+        }
+        writer.flush()
+    }
+
+    /// Adds the entries of a saved table, returns how many were read
+    pub fn load(&self, path: impl AsRef<Path>, game: &str, seed: u64) -> io::Result<usize> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        read_header(
+            &mut reader,
+            MAGIC,
+            VERSION,
+            game,
+            seed,
+            "transposition table",
+        )?;
+        let count = u64::from_le_bytes(read_array(&mut reader)?) as usize;
+
+        // ! ========================= Entries =========================
+        let mut move_bytes = vec![0; M::ENCODED_SIZE];
+        for _ in 0..count {
+            let key = i64::from_le_bytes(read_array(&mut reader)?) as isize;
+            let check = u32::from_le_bytes(read_array(&mut reader)?);
+            let value = i64::from_le_bytes(read_array(&mut reader)?) as isize;
+            let depth = i32::from_le_bytes(read_array(&mut reader)?) as isize;
+            let [state_type] = read_array::<1>(&mut reader)?;
+            let state_type = state_type_from_byte(state_type)?;
+            reader.read_exact(&mut move_bytes)?;
+            let best_move = M::decode(&move_bytes)
+                .ok_or_else(|| invalid(String::from("Invalid move in table")))?;
+            self.add_for_next_search(key, check, (value, depth, state_type, best_move));
+        }
+        Ok(count)
+    }
+}
@@ -0,0 +1,20 @@
+use super::{
+    super::traits::{EndgameTablebase, ScoreOfState},
+    mate_score::add_plies,
+    quiescence::static_score,
+};
+
+/// `static_score` that takes the exact score from `tablebase` when the state is in it
+pub fn tablebase_score<T: ScoreOfState, B: EndgameTablebase<T>>(
+    state: &T,
+    tablebase: &B,
+    color: bool,
+    ply: usize,
+    alpha: isize,
+    beta: isize,
+) -> isize {
+    match tablebase.probe(state, color) {
+        Some(score) => add_plies(score, ply as isize),
+        None => static_score(state, color, ply, alpha, beta),
+    }
+}
@@ -1,40 +1,452 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    mem::size_of,
+    sync::{
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
 
-use super::transposition_state_type::TranspositionStateType;
+use super::{
+    super::traits::{ChildStates, StateHash, TerminalState},
+    mate_score::{add_plies, remove_plies},
+    replacement_policy::ReplacementPolicy,
+    transposition_state_type::TranspositionStateType,
+};
 
-/// A hashmap used to store known states
-unsafe impl<M> Send for TranspositionTable<M> where M: Send {}
-unsafe impl<M> Sync for TranspositionTable<M> where M: Sync {}
-pub struct TranspositionTable<M> {
-    map: HashMap<isize, (isize, isize, TranspositionStateType, M)>,
+type Entry<M> = (isize, isize, TranspositionStateType, M);
+
+/// Entries per bucket, a probe only looks inside one bucket
+const BUCKET_SIZE: usize = 4;
+/// Size used by `default`
+pub const DEFAULT_SIZE_MB: usize = 64;
+/// Buckets looked at to estimate the fill rate
+const FILL_RATE_SAMPLE: usize = 1000;
+
+#[derive(Clone, Copy)]
+struct Slot<M> {
+    key: isize,
+    /// `StateHash::verification` of the stored state
+    check: u32,
+    entry: Entry<M>,
+    generation: u8,
 }
 
-impl<M> Default for TranspositionTable<M> {
+impl<M: Default> Default for Slot<M> {
     fn default() -> Self {
         Self {
-            map: HashMap::new(),
+            key: 0,
+            check: 0,
+            entry: (0, 0, TranspositionStateType::Unknown, M::default()),
+            generation: 0,
         }
     }
 }
+
+impl<M> Slot<M> {
+    fn is_empty(&self) -> bool {
+        self.entry.2 == TranspositionStateType::Unknown
+    }
+    fn depth(&self) -> isize {
+        self.entry.1
+    }
+}
+
+type Bucket<M> = [Slot<M>; BUCKET_SIZE];
+
+#[derive(Default)]
+struct Counters {
+    probes: AtomicUsize,
+    hits: AtomicUsize,
+    exact_cutoffs: AtomicUsize,
+    lower_bound_cutoffs: AtomicUsize,
+    upper_bound_cutoffs: AtomicUsize,
+    stores: AtomicUsize,
+    overwrites: AtomicUsize,
+    collisions: AtomicUsize,
+}
+
+fn increment(counter: &AtomicUsize) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A snapshot of how the table has been used since the last reset
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TableStatistics {
+    pub probes: usize,
+    pub hits: usize,
+    pub exact_cutoffs: usize,
+    pub lower_bound_cutoffs: usize,
+    pub upper_bound_cutoffs: usize,
+    pub stores: usize,
+    /// Stores that pushed out the entry of another state
+    pub overwrites: usize,
+    /// Probes that found the right hash but the wrong verification key
+    pub collisions: usize,
+    /// Share of the entries in use, estimated from the first buckets
+    pub fill_rate: f64,
+}
+
+impl TableStatistics {
+    pub fn hit_rate(&self) -> f64 {
+        match self.probes {
+            0 => 0.0,
+            probes => self.hits as f64 / probes as f64,
+        }
+    }
+    pub fn cutoffs(&self) -> usize {
+        self.exact_cutoffs + self.lower_bound_cutoffs + self.upper_bound_cutoffs
+    }
+}
+
+impl Display for TableStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "probes: {}, hits: {} ({:.1}%)",
+            self.probes,
+            self.hits,
+            self.hit_rate() * 100.0
+        )?;
+        writeln!(
+            f,
+            "cutoffs: {} (exact: {}, lower bound: {}, upper bound: {})",
+            self.cutoffs(),
+            self.exact_cutoffs,
+            self.lower_bound_cutoffs,
+            self.upper_bound_cutoffs
+        )?;
+        writeln!(
+            f,
+            "stores: {}, overwrites: {}, collisions: {}",
+            self.stores, self.overwrites, self.collisions
+        )?;
+        write!(f, "fill rate: {:.1}%", self.fill_rate * 100.0)
+    }
+}
+
+/// A fixed size table of known states, safe to share between search threads
+pub struct TranspositionTable<M> {
+    buckets: Vec<Mutex<Bucket<M>>>,
+    policy: ReplacementPolicy,
+    generation: AtomicU8,
+    /// The current generation was started by `new_ponder_search` for the next search
+    pondered: AtomicBool,
+    counters: Counters,
+}
+
+impl<M> Default for TranspositionTable<M>
+where
+    M: Default + Copy,
+{
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE_MB, ReplacementPolicy::TwoTier)
+    }
+}
 impl<M> TranspositionTable<M>
 where
     M: Default + Copy,
+{
+    /// Allocates the largest power of two number of buckets that fits in `size_mb` megabytes
+    pub fn new(size_mb: usize, policy: ReplacementPolicy) -> Self {
+        let fits = size_mb * 1024 * 1024 / size_of::<Mutex<Bucket<M>>>();
+        let bucket_count = match fits {
+            0 => 1,
+            x => 1 << x.ilog2(),
+        };
+        Self {
+            buckets: (0..bucket_count)
+                .map(|_| Mutex::new([Slot::default(); BUCKET_SIZE]))
+                .collect(),
+            policy,
+            generation: AtomicU8::new(0),
+            pondered: AtomicBool::new(false),
+            counters: Counters::default(),
+        }
+    }
+}
+impl<M> TranspositionTable<M>
+where
+    M: Default + Copy + PartialEq,
 {
     pub const RETRY_VALUE: isize = 0;
+    /// Number of entries the table can hold
     pub fn cap(&self) -> usize {
-        self.map.capacity()
+        self.buckets.len() * BUCKET_SIZE
     }
+    /// Number of entries in use
     pub fn size(&self) -> usize {
-        self.map.len()
+        self.buckets
+            .iter()
+            .map(|bucket| {
+                let bucket = bucket.lock().unwrap();
+                bucket.iter().filter(|slot| !slot.is_empty()).count()
+            })
+            .sum()
+    }
+    pub fn policy(&self) -> ReplacementPolicy {
+        self.policy
+    }
+    pub fn clear(&self) {
+        for bucket in &self.buckets {
+            *bucket.lock().unwrap() = [Slot::default(); BUCKET_SIZE];
+        }
+    }
+    /// Ages every stored entry, call once before every new root search
+    pub fn new_search(&self) {
+        // A ponder search already started the generation of this search
+        if !self.pondered.swap(false, Ordering::Relaxed) {
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    /// Starts the generation of the next root search early, call before pondering on it.
+    /// The `new_search` of that search keeps the generation, so the pondered entries stay fresh.
+    pub fn new_ponder_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.pondered.store(true, Ordering::Relaxed);
+    }
+
+    fn bucket(&self, key: isize) -> &Mutex<Bucket<M>> {
+        &self.buckets[key as usize & (self.buckets.len() - 1)]
+    }
+
+    pub fn statistics(&self) -> TableStatistics {
+        let counters = &self.counters;
+        let sample = &self.buckets[..self.buckets.len().min(FILL_RATE_SAMPLE)];
+        let used: usize = sample
+            .iter()
+            .map(|bucket| {
+                let bucket = bucket.lock().unwrap();
+                bucket.iter().filter(|slot| !slot.is_empty()).count()
+            })
+            .sum();
+        TableStatistics {
+            probes: counters.probes.load(Ordering::Relaxed),
+            hits: counters.hits.load(Ordering::Relaxed),
+            exact_cutoffs: counters.exact_cutoffs.load(Ordering::Relaxed),
+            lower_bound_cutoffs: counters.lower_bound_cutoffs.load(Ordering::Relaxed),
+            upper_bound_cutoffs: counters.upper_bound_cutoffs.load(Ordering::Relaxed),
+            stores: counters.stores.load(Ordering::Relaxed),
+            overwrites: counters.overwrites.load(Ordering::Relaxed),
+            collisions: counters.collisions.load(Ordering::Relaxed),
+            fill_rate: used as f64 / (sample.len() * BUCKET_SIZE) as f64,
+        }
+    }
+    pub fn reset_statistics(&self) {
+        let counters = &self.counters;
+        for counter in [
+            &counters.probes,
+            &counters.hits,
+            &counters.exact_cutoffs,
+            &counters.lower_bound_cutoffs,
+            &counters.upper_bound_cutoffs,
+            &counters.stores,
+            &counters.overwrites,
+            &counters.collisions,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
     }
 
-    pub(crate) fn add(&mut self, key: isize, value: (isize, isize, TranspositionStateType, M)) {
-        self.map.insert(key, value);
+    /// Every stored entry with its hash and verification key
+    pub(crate) fn entries(&self) -> Vec<(isize, u32, Entry<M>)> {
+        let mut entries = Vec::new();
+        for bucket in &self.buckets {
+            let bucket = bucket.lock().unwrap();
+            for slot in bucket.iter().filter(|slot| !slot.is_empty()) {
+                entries.push((slot.key, slot.check, slot.entry));
+            }
+        }
+        entries
+    }
+
+    /// Looks up `state`, counted in the statistics
+    /// Mate scores are stored relative to the state, `ply` is the distance from the root
+    pub(crate) fn probe<T: StateHash>(&self, state: &T, color: bool, ply: usize) -> Entry<M> {
+        increment(&self.counters.probes);
+        let mut entry = self.get(state.hash(color), state.verification(color));
+        if entry.2 != TranspositionStateType::Unknown {
+            increment(&self.counters.hits);
+        }
+        entry.0 = add_plies(entry.0, ply as isize);
+        entry
+    }
+    pub(crate) fn store<T: StateHash>(
+        &self,
+        state: &T,
+        color: bool,
+        ply: usize,
+        mut value: Entry<M>,
+    ) {
+        increment(&self.counters.stores);
+        value.0 = remove_plies(value.0, ply as isize);
+        self.add(state.hash(color), state.verification(color), value);
+    }
+    /// Called by a search that returned because of the entry it probed
+    pub(crate) fn record_cutoff(&self, state_type: TranspositionStateType) {
+        match state_type {
+            TranspositionStateType::Exact => increment(&self.counters.exact_cutoffs),
+            TranspositionStateType::LowerBound => increment(&self.counters.lower_bound_cutoffs),
+            TranspositionStateType::UpperBound => increment(&self.counters.upper_bound_cutoffs),
+            TranspositionStateType::Unknown => (),
+        }
+    }
+
+    pub(crate) fn add(&self, key: isize, check: u32, value: Entry<M>) {
+        self.add_in_generation(key, check, value, self.generation.load(Ordering::Relaxed));
+    }
+    /// Loaded entries belong to the next search, so its `new_search` doesn't make them stale
+    pub(crate) fn add_for_next_search(&self, key: isize, check: u32, value: Entry<M>) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let generation = match self.pondered.load(Ordering::Relaxed) {
+            true => generation,
+            false => generation.wrapping_add(1),
+        };
+        self.add_in_generation(key, check, value, generation);
     }
-    pub(crate) fn get(&self, key: isize) -> (isize, isize, TranspositionStateType, M) {
-        match self.map.get(&key) {
-            Some(&x) => x,
+    fn add_in_generation(&self, key: isize, check: u32, value: Entry<M>, generation: u8) {
+        let mut bucket = self.bucket(key).lock().unwrap();
+        let new_slot = Slot {
+            key,
+            check,
+            entry: value,
+            generation,
+        };
+
+        // ! ================ Same state or a free slot ================
+        if let Some(slot) = bucket
+            .iter_mut()
+            .find(|slot| !slot.is_empty() && slot.key == key)
+        {
+            if slot.check != check {
+                increment(&self.counters.overwrites);
+            }
+            *slot = new_slot;
+            return;
+        }
+        if let Some(slot) = bucket.iter_mut().find(|slot| slot.is_empty()) {
+            *slot = new_slot;
+            return;
+        }
+
+        // ! ================ Pick a victim ================
+        let age = |slot: &Slot<M>| generation.wrapping_sub(slot.generation) as isize;
+        // Stale entries go first, then the shallow ones
+        let worth = |slot: &Slot<M>| slot.depth() - 8 * age(slot);
+        let index = |slots: &[Slot<M>]| {
+            (0..slots.len())
+                .min_by_key(|&index| worth(&slots[index]))
+                .unwrap()
+        };
+        match self.policy {
+            ReplacementPolicy::AlwaysReplace => {
+                let victim = match bucket.iter().position(|slot| age(slot) > 0) {
+                    Some(stale) => stale,
+                    None => key as usize / self.buckets.len() % BUCKET_SIZE,
+                };
+                bucket[victim] = new_slot;
+            }
+            ReplacementPolicy::DepthPreferred => {
+                let victim = index(&bucket[..]);
+                if age(&bucket[victim]) > 0 || bucket[victim].depth() <= value.1 {
+                    bucket[victim] = new_slot;
+                } else {
+                    return;
+                }
+            }
+            ReplacementPolicy::TwoTier => {
+                let (depth_tier, always_tier) = bucket.split_at_mut(BUCKET_SIZE / 2);
+                let victim = index(depth_tier);
+                if age(&depth_tier[victim]) > 0 || depth_tier[victim].depth() <= value.1 {
+                    // The old deep entry moves down to the always replace tier
+                    let demoted = depth_tier[victim];
+                    depth_tier[victim] = new_slot;
+                    always_tier[index(always_tier)] = demoted;
+                } else {
+                    always_tier[index(always_tier)] = new_slot;
+                }
+            }
+        }
+        increment(&self.counters.overwrites);
+    }
+    /// An entry only counts when both the hash and the verification key match
+    pub(crate) fn get(&self, key: isize, check: u32) -> Entry<M> {
+        let bucket = self.bucket(key).lock().unwrap();
+        match bucket
+            .iter()
+            .find(|slot| !slot.is_empty() && slot.key == key)
+        {
+            Some(slot) if slot.check == check => slot.entry,
+            Some(_) => {
+                increment(&self.counters.collisions);
+                (0, 0, TranspositionStateType::Unknown, M::default())
+            }
             None => (0, 0, TranspositionStateType::Unknown, M::default()),
         }
     }
+
+    /// The stored best move of `state`, if there is one and it is legal there
+    pub fn stored_move<T>(&self, state: &T, color: bool) -> Option<M>
+    where
+        T: ChildStates<M> + StateHash,
+    {
+        let (_, _, state_type, best_move) = self.get(state.hash(color), state.verification(color));
+        match state_type != TranspositionStateType::Unknown
+            && state.child_states(color).contains(&best_move)
+        {
+            true => Some(best_move),
+            false => None,
+        }
+    }
+
+    /// Follows the stored best moves from `state`, the line the search expects to be played.
+    /// Stops at a missing entry, a stored move that isn't legal, a repeated hash or a terminal state.
+    pub fn principal_variation<T>(&self, state: &T, color: bool) -> Vec<M>
+    where
+        T: ChildStates<M> + StateHash + TerminalState + Copy,
+    {
+        let mut pv = Vec::new();
+        let mut seen = HashSet::new();
+        let mut state = *state;
+        let mut color = color;
+        while !state.is_terminal() && seen.insert(state.hash(color)) {
+            match self.stored_move(&state, color) {
+                Some(best_move) => {
+                    pv.push(best_move);
+                    state = state + best_move;
+                    color = !color;
+                }
+                None => break,
+            }
+        }
+        pv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReplacementPolicy, TranspositionStateType, TranspositionTable, BUCKET_SIZE};
+
+    #[test]
+    fn pondered_entries_survive_the_next_new_search() {
+        // Below one bucket the table gets a single one, so every key competes for it
+        let table = TranspositionTable::<u8>::new(0, ReplacementPolicy::AlwaysReplace);
+        let entry = |depth| (0, depth, TranspositionStateType::Exact, 0);
+        table.new_search();
+        for key in 0..BUCKET_SIZE as isize {
+            table.add(key, 0, entry(8));
+        }
+
+        table.new_ponder_search();
+        table.add(100, 0, entry(1));
+        assert_eq!(table.get(100, 0).2, TranspositionStateType::Exact);
+
+        // The search that follows the ponder overwrites the entries of the one before it first
+        table.new_search();
+        for key in 200..200 + BUCKET_SIZE as isize - 1 {
+            table.add(key, 0, entry(1));
+        }
+        assert_eq!(table.get(100, 0).2, TranspositionStateType::Exact);
+    }
 }
@@ -1,19 +1,73 @@
-use std::{
-    sync::{Arc, Mutex},
-    time::Duration,
-};
+use std::time::Duration;
 
-use super::enhancements::transposition_table::TranspositionTable;
-/// A basic or ID alpha beta search
-pub type DepthSearchFunction<T> = fn(&T, isize, bool) -> isize;
-/// A Timed ID alpha beta search
-pub type TimedSearchFunction<T> = fn(&T, Duration, bool) -> isize;
+use super::enhancements::{
+    move_ordering::MoveOrdering, search_state::SearchContext,
+    transposition_table::TranspositionTable,
+};
+/// A basic or ID alpha beta search, `None` when the context stopped it
+pub type DepthSearchFunction<T> = fn(&T, isize, bool, &mut SearchContext) -> Option<isize>;
+/// A Timed ID alpha beta search, `None` when not even the first iteration finished
+pub type TimedSearchFunction<T> = fn(&T, Duration, bool, &mut SearchContext) -> Option<isize>;
 /// A Timed ID with min depth alpha beta search
-pub type TimedDepthSearchFunction<T> = fn(&T, isize, Duration, bool) -> isize;
-/// A basic or ID alpha beta search with TT
-pub type DepthSearchFunctionWithTable<M, T> =
-    fn(&T, isize, bool, &mut TranspositionTable<M>) -> isize;
-pub type DepthSearchFunctionWithTableMutex<M, T> =
-    fn(&T, isize, bool, Arc<Mutex<&mut TranspositionTable<M>>>) -> isize;
-pub type TimedSearchFunctionWithTable<M, T> =
-    fn(&T, bool, Duration, &mut TranspositionTable<M>) -> isize;
+pub type TimedDepthSearchFunction<T> =
+    fn(&T, isize, Duration, bool, &mut SearchContext) -> Option<isize>;
+/// A basic or ID alpha beta search with TT, the ordering is the one of the root search that calls
+/// it and may be ignored
+pub type DepthSearchFunctionWithTable<M, T> = fn(
+    &T,
+    isize,
+    bool,
+    &TranspositionTable<M>,
+    &mut MoveOrdering<M>,
+    &mut SearchContext,
+) -> Option<isize>;
+/// A `DepthSearchFunctionWithTable` or a closure with the same arguments, like one that owns the
+/// tablebase its leaves probe
+pub trait DepthSearchWithTable<M, T>:
+    Fn(
+    &T,
+    isize,
+    bool,
+    &TranspositionTable<M>,
+    &mut MoveOrdering<M>,
+    &mut SearchContext,
+) -> Option<isize>
+{
+}
+impl<M, T, F> DepthSearchWithTable<M, T> for F where
+    F: Fn(
+        &T,
+        isize,
+        bool,
+        &TranspositionTable<M>,
+        &mut MoveOrdering<M>,
+        &mut SearchContext,
+    ) -> Option<isize>
+{
+}
+pub type TimedSearchFunctionWithTable<M, T> = fn(
+    &T,
+    bool,
+    Duration,
+    &TranspositionTable<M>,
+    &mut MoveOrdering<M>,
+    &mut SearchContext,
+) -> Option<isize>;
+/// An alpha beta search with TT and a window, the window and the score are from the view of `true`.
+/// `nega_with_table` has the same signature but works from the view of the mover, so it is
+/// silently wrong when `false` is to move; pass `nega_with_table_for_true` instead.
+pub type WindowSearchFunctionWithTable<M, T> =
+    fn(&T, isize, bool, &TranspositionTable<M>, &mut SearchContext, isize, isize) -> Option<isize>;
+/// Scores a depth 0 leaf from the view of the side to move, given its ply and the window it is
+/// searched with. A fn like `static_leaf`, or a closure that owns what it probes.
+pub trait LeafFunction<T>:
+    Fn(&T, bool, usize, &mut SearchContext, isize, isize) -> Option<isize>
+{
+}
+impl<T, F> LeafFunction<T> for F where
+    F: Fn(&T, bool, usize, &mut SearchContext, isize, isize) -> Option<isize>
+{
+}
+/// A search of games with chance nodes, the window and the score are from the view of the mover
+pub type ChanceSearchFunction<T> =
+    fn(&T, isize, bool, &mut SearchContext, isize, isize) -> Option<isize>;
@@ -1,5 +1,11 @@
+pub mod agents;
+pub mod clock;
 pub mod enhancements;
 pub mod function_types;
 pub mod move_finders;
+pub mod perft;
+pub mod player;
+pub mod runner;
+pub mod tournament;
 pub mod traits;
 // Made by Philip Bollen
@@ -4,16 +4,35 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crossbeam::{channel::unbounded, thread};
+use crossbeam::thread;
 use rand::{rngs::StdRng, seq::SliceRandom};
 
 use super::{
-    enhancements::transposition_table::TranspositionTable,
+    clock::TimeManager,
+    enhancements::{
+        aspiration_window::{aspiration_search, AspirationWindow},
+        base::pvs_with_table_ordered,
+        mate_score::{add_plies, mate_distance},
+        mcts::{MonteCarloTree, SearchBudget},
+        move_ordering::MoveOrdering,
+        mtdf::mtdf,
+        multi_player::{max_n, paranoid},
+        quiescence::static_score,
+        search_result::SearchResult,
+        search_state::{SearchContext, StopToken},
+        selective_search::{nega_with_table_selective, SelectiveSearch},
+        transposition_table::TranspositionTable,
+    },
     function_types::{
-        DepthSearchFunction, DepthSearchFunctionWithTable, TimedSearchFunction,
-        TimedSearchFunctionWithTable,
+        ChanceSearchFunction, DepthSearchFunction, DepthSearchFunctionWithTable,
+        DepthSearchWithTable, TimedSearchFunction, TimedSearchFunctionWithTable,
+        WindowSearchFunctionWithTable,
+    },
+    player::Player,
+    traits::{
+        ChanceStates, ChildStates, EndgameTablebase, MultiPlayerStates, NullMove, ScoreOfState,
+        ScoresOfState, StateHash, TerminalState,
     },
-    traits::{ChildStates, ScoreOfState, TerminalState},
 };
 pub fn random_agent<T: ChildStates<M>, M: Copy>(
     current_move: T,
@@ -24,157 +43,548 @@ pub fn random_agent<T: ChildStates<M>, M: Copy>(
 }
 pub fn human_agent<T: ChildStates<M>, M: Display + Copy>(current_move: &T, color: bool) -> M {
     let children = current_move.child_states(color);
-    let mut index = 1;
-    for child in children.iter() {
+    for (index, child) in (1..).zip(children.iter()) {
         println!("{}index:{}\n", child, index);
-        index += 1;
-    }
-    let mut string = String::new();
-    match stdin().read_line(&mut string) {
-        Ok(_) => {
-            let numbers = string
-                .strip_suffix("\r\n")
-                .unwrap()
-                .parse::<usize>()
-                .unwrap();
-            children[numbers - 1]
+    }
+    loop {
+        let mut string = String::new();
+        match stdin().read_line(&mut string) {
+            Ok(0) => panic!("stdin was closed"),
+            Ok(_) => match string.trim().parse::<usize>() {
+                Ok(number) if (1..=children.len()).contains(&number) => {
+                    return children[number - 1]
+                }
+                _ => println!("Type a number between 1 and {}", children.len()),
+            },
+            _ => panic!(),
         }
-        _ => panic!(),
     }
 }
 pub fn find_best_move_tt<
-    M: Copy,
-    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq,
+    M: Default + Copy + PartialEq,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq + StateHash,
 >(
     state: &T,
     depth: isize,
     color: bool,
-    table: &mut TranspositionTable<M>,
+    table: &TranspositionTable<M>,
     search_function: DepthSearchFunctionWithTable<M, T>,
-) -> M {
+) -> SearchResult<M> {
+    let start = Instant::now();
     let mut best_score = match color {
         true => isize::MIN + 1,
         false => isize::MAX,
     };
     let moves = state.child_states(color);
     if moves.len() == 1 {
-        return moves[0];
+        return forced_move(state, moves[0], start);
     }
     let mut best_move = moves[0];
 
+    table.new_search();
+    let mut ordering = MoveOrdering::default();
+    let mut context = SearchContext::new();
     for next_move in moves {
         let child_state = *state + next_move;
-        let score = search_function(&child_state, depth, !color, table);
+        // A move the search gave up on has no score, it can't be the best one
+        let Some(score) = search_function(
+            &child_state,
+            depth,
+            !color,
+            table,
+            &mut ordering,
+            &mut context,
+        ) else {
+            continue;
+        };
+        let score = add_plies(score, 1);
         if color && (score > best_score) || !color && (score < best_score) {
             best_move = next_move;
             best_score = score;
         }
     }
-    best_move
+    let mut result = search_result(best_move, best_score, depth, context.nodes(), start);
+    result.pv = principal_variation(state, best_move, color, table);
+    result
 }
 
 pub fn find_best_move_t_tt<T, M>(
     state: &T,
     max_time: Duration,
     color: bool,
-    table: &mut TranspositionTable<M>,
+    table: &TranspositionTable<M>,
     search_function: TimedSearchFunctionWithTable<M, T>,
-) -> M
+) -> SearchResult<M>
 where
-    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq,
-    M: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq + StateHash,
+    M: Default + Copy + PartialEq,
 {
+    let start = Instant::now();
     let mut best_score = match color {
         true => isize::MIN + 1,
         false => isize::MAX,
     };
     let moves = state.child_states(color);
     if moves.len() == 1 {
-        return moves[0];
+        return forced_move(state, moves[0], start);
     }
     let mut best_move = moves[0];
 
+    table.new_search();
+    let mut ordering = MoveOrdering::default();
+    let mut context = SearchContext::new();
     let total_time = max_time / moves.len() as u32;
     for next_move in moves {
         let child = *state + next_move;
-        let score = search_function(&child, !color, total_time, table);
+        // Not even depth 1 of the move finished in its time
+        let Some(score) = search_function(
+            &child,
+            !color,
+            total_time,
+            table,
+            &mut ordering,
+            &mut context,
+        ) else {
+            continue;
+        };
+        let score = add_plies(score, 1);
         if color && (score > best_score) || !color && (score < best_score) {
             best_move = next_move;
             best_score = score;
         }
     }
-    best_move
+    let mut result = search_result(
+        best_move,
+        best_score,
+        context.depth(),
+        context.nodes(),
+        start,
+    );
+    result.pv = principal_variation(state, best_move, color, table);
+    result
 }
-pub fn find_best_move_t_tt_id<T, M>(
+/// Timed ID that stops where `time` says, use `TimeManager::fixed` for a fixed time per move
+pub fn find_best_move_t_tt_id<T, M, S>(
     state: &T,
-    max_time: Duration,
+    time: TimeManager,
     color: bool,
-    table: &mut TranspositionTable<M>,
-    search_function: DepthSearchFunctionWithTable<M, T>,
-) -> M
+    table: &TranspositionTable<M>,
+    search_function: &S,
+) -> SearchResult<M>
 where
-    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Sync + Copy,
-    M: Send + Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Sync + Copy + StateHash,
+    M: Send + Default + Copy + PartialEq,
+    S: DepthSearchWithTable<M, T> + Sync,
 {
-    let mut best_score = match color {
-        true => isize::MIN + 1,
-        false => isize::MAX,
-    };
+    find_best_move_t_tt_smp(state, time, color, table, 1, search_function)
+}
+
+/// Lazy SMP, `threads` workers run iterative deepening on the same root and share `table`.
+/// The helpers only fill the table, the move is taken from the main worker.
+pub fn find_best_move_t_tt_smp<T, M, S>(
+    state: &T,
+    mut time: TimeManager,
+    color: bool,
+    table: &TranspositionTable<M>,
+    threads: usize,
+    search_function: &S,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Sync + Copy + StateHash,
+    M: Send + Default + Copy + PartialEq,
+    S: DepthSearchWithTable<M, T> + Sync,
+{
+    let start = Instant::now();
     let moves = state.child_states(color);
 
     if moves.len() == 1 {
-        return moves[0];
+        return forced_move(state, moves[0], start);
     }
 
-    let mut best_move = moves[0];
-    let mut depth = 1;
+    table.new_search();
+    let stop = StopToken::with_deadline(start + time.hard_limit());
+    let mut context = SearchContext::with_stop_token(stop.clone());
+    let ((best_move, best_score, completed_depth), helper_nodes) = thread::scope(|s| {
+        // !========================= Start the helpers ==================================
+        let helpers: Vec<_> = (1..threads)
+            .map(|id| {
+                let stop = stop.clone();
+                let mut moves = moves.clone();
+                s.spawn(move |_| {
+                    let mut context = SearchContext::with_stop_token(stop);
+                    // Every other helper starts a ply deeper and each one in its own move order,
+                    // so they don't all repeat the work of the main worker
+                    let first_depth = 1 + (id % 2) as isize;
+                    let shift = id % moves.len();
+                    moves.rotate_left(shift);
+                    root_iterative_deepening(
+                        state,
+                        color,
+                        table,
+                        &mut context,
+                        search_function,
+                        moves,
+                        first_depth,
+                        None,
+                    );
+                    context.nodes()
+                })
+            })
+            .collect();
+
+        let main_result = root_iterative_deepening(
+            state,
+            color,
+            table,
+            &mut context,
+            search_function,
+            moves,
+            1,
+            Some((&mut time, start)),
+        );
+        stop.stop();
+        let helper_nodes: usize = helpers
+            .into_iter()
+            .map(|helper| helper.join().unwrap())
+            .sum();
+        (main_result, helper_nodes)
+    })
+    .unwrap();
+    context.add_nodes(helper_nodes);
+
+    let mut result = search_result(
+        best_move,
+        best_score,
+        completed_depth,
+        context.nodes(),
+        start,
+    );
+    result.pv = principal_variation(state, best_move, color, table);
+    result
+}
+
+/// Iterative deepening into `table` until `stop` fires, for searching on the time of the opponent.
+/// Returns what was found by then. The entries belong to the next search, so its `new_search`
+/// doesn't age them.
+pub fn ponder_t_tt<T, M, S>(
+    state: &T,
+    color: bool,
+    table: &TranspositionTable<M>,
+    search_function: &S,
+    stop: StopToken,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+    M: Default + Copy + PartialEq,
+    S: DepthSearchWithTable<M, T>,
+{
     let start = Instant::now();
-    while start.elapsed() < max_time {
-        for next_move in &moves {
+    let moves = state.child_states(color);
+    if moves.len() == 1 {
+        return forced_move(state, moves[0], start);
+    }
+
+    table.new_ponder_search();
+    let mut context = SearchContext::with_stop_token(stop);
+    let (best_move, best_score, completed_depth) = root_iterative_deepening(
+        state,
+        color,
+        table,
+        &mut context,
+        search_function,
+        moves,
+        1,
+        None,
+    );
+    let mut result = search_result(
+        best_move,
+        best_score,
+        completed_depth,
+        context.nodes(),
+        start,
+    );
+    result.pv = principal_variation(state, best_move, color, table);
+    result
+}
+
+/// Searches every root move one depth at a time until `context` is stopped, or until `time`,
+/// with the start of the search, says to stop.
+/// Returns the best move, its score and the depth of the last completed iteration.
+#[allow(clippy::too_many_arguments)]
+fn root_iterative_deepening<T, M, S>(
+    state: &T,
+    color: bool,
+    table: &TranspositionTable<M>,
+    context: &mut SearchContext,
+    search_function: &S,
+    mut ordered_moves: Vec<M>,
+    first_depth: isize,
+    mut time: Option<(&mut TimeManager, Instant)>,
+) -> (M, isize, isize)
+where
+    T: ChildStates<M> + Copy,
+    M: Copy + PartialEq,
+    S: DepthSearchWithTable<M, T>,
+{
+    let mut best_score = match color {
+        true => isize::MIN + 1,
+        false => isize::MAX,
+    };
+    let mut best_move = ordered_moves[0];
+    let mut depth = first_depth;
+    let mut completed_depth = 0;
+    // Every worker has its own tables, they are only used by one thread
+    let mut ordering = MoveOrdering::default();
+    while !context.check_stop() {
+        let mut iteration_score = match color {
+            true => isize::MIN + 1,
+            false => isize::MAX,
+        };
+        let mut second_score = iteration_score;
+        let mut iteration_move = ordered_moves[0];
+        for next_move in &ordered_moves {
             let child = *state + *next_move;
-            let (scval, rcval) = unbounded();
-
-            // !========================= Start a new thread ==================================
-            thread::scope(|s| {
-                s.spawn(|_| {
-                    let val = search_function(&child, depth, !color, table);
-                    scval.send(val).unwrap();
-                    drop(scval);
-                });
-            })
-            .unwrap();
-            if max_time < start.elapsed() {
-                break;
+            // !============== Fall back to the last completed iteration on a timeout =============
+            let Some(score) = search_function(&child, depth, !color, table, &mut ordering, context)
+            else {
+                return (best_move, best_score, completed_depth);
+            };
+            let score = add_plies(score, 1);
+            if color && (score > iteration_score) || !color && (score < iteration_score) {
+                iteration_move = *next_move;
+                second_score = iteration_score;
+                iteration_score = score;
+            } else if color && (score > second_score) || !color && (score < second_score) {
+                second_score = score;
             }
-            // !============================ Get result if there is no timeout =================
-            let score = match rcval.recv_timeout(max_time - start.elapsed()) {
-                Ok(x) => x,
-                Err(_) => break,
+        }
+        let best_move_changed = completed_depth > 0 && iteration_move != best_move;
+        best_move = iteration_move;
+        best_score = iteration_score;
+        completed_depth = depth;
+        if mate_within(best_score, depth) {
+            break;
+        }
+        if let Some((time, start)) = time.as_mut() {
+            let lead = match color {
+                true => iteration_score.saturating_sub(second_score),
+                false => second_score.saturating_sub(iteration_score),
             };
-
-            if color && (score > best_score) || !color && (score < best_score) {
-                best_move = *next_move;
-                best_score = score;
+            time.iteration_done(best_move_changed, lead);
+            if !time.keep_searching(start.elapsed()) {
+                break;
             }
         }
+
+        // The best move of this iteration is searched first in the next one
+        let index = ordered_moves.iter().position(|&m| m == best_move).unwrap();
+        ordered_moves[..=index].rotate_right(1);
+        depth += 1;
+    }
+    (best_move, best_score, completed_depth)
+}
+/// Timed ID that searches the root itself with aspiration windows and takes the move from the table
+pub fn find_best_move_t_tt_aspiration<T, M>(
+    state: &T,
+    max_time: Duration,
+    color: bool,
+    table: &TranspositionTable<M>,
+    window: AspirationWindow,
+    search_function: WindowSearchFunctionWithTable<M, T>,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Copy + StateHash,
+    M: Default + Copy + PartialEq,
+{
+    root_search_t_tt(
+        state,
+        max_time,
+        color,
+        table,
+        |depth, previous, context| match previous {
+            None => search_function(
+                state,
+                depth,
+                color,
+                table,
+                context,
+                isize::MIN + 1,
+                isize::MAX,
+            ),
+            Some(previous) => aspiration_search(
+                state,
+                depth,
+                color,
+                table,
+                context,
+                previous,
+                window,
+                search_function,
+            ),
+        },
+    )
+}
+
+/// Timed ID where every depth is an MTD(f) search of the root, the move is taken from the table
+pub fn find_best_move_t_tt_mtdf<T, M>(
+    state: &T,
+    max_time: Duration,
+    color: bool,
+    table: &TranspositionTable<M>,
+    search_function: WindowSearchFunctionWithTable<M, T>,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Copy + StateHash,
+    M: Default + Copy + PartialEq,
+{
+    root_search_t_tt(state, max_time, color, table, |depth, previous, context| {
+        mtdf(
+            state,
+            depth,
+            color,
+            table,
+            context,
+            previous.unwrap_or(0),
+            search_function,
+        )
+    })
+}
+
+/// Timed ID with a principal variation search of the root, with its own killer, history and
+/// countermove tables for the whole search
+pub fn find_best_move_t_tt_pvs<T, M>(
+    state: &T,
+    max_time: Duration,
+    color: bool,
+    table: &TranspositionTable<M>,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Copy + StateHash,
+    M: Default + Copy + Eq + Ord,
+{
+    let mut ordering = MoveOrdering::new();
+    root_search_t_tt(state, max_time, color, table, |depth, _, context| {
+        let score = pvs_with_table_ordered(
+            state,
+            depth,
+            color,
+            table,
+            &mut ordering,
+            context,
+            isize::MIN + 1,
+            isize::MAX,
+        )?;
+        Some(match color {
+            true => score,
+            false => -score,
+        })
+    })
+}
+
+/// Timed ID of a negamax search of the root with the forward pruning of `options`
+pub fn find_best_move_t_tt_selective<T, M>(
+    state: &T,
+    max_time: Duration,
+    color: bool,
+    table: &TranspositionTable<M>,
+    options: SelectiveSearch,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Copy + StateHash + NullMove,
+    M: Default + Copy + Eq + Ord,
+{
+    let mut ordering = MoveOrdering::new();
+    root_search_t_tt(state, max_time, color, table, |depth, _, context| {
+        let score = nega_with_table_selective(
+            state,
+            depth,
+            color,
+            table,
+            &mut ordering,
+            context,
+            &options,
+            isize::MIN + 1,
+            isize::MAX,
+        )?;
+        Some(match color {
+            true => score,
+            false => -score,
+        })
+    })
+}
+
+/// Runs `search_depth` on the root for depth 1, 2, ... until the time is up, passing it the score
+/// of the previous completed depth and the context of the search, a depth it didn't finish returns
+/// `None`. The best move is read back from the table after every depth.
+fn root_search_t_tt<T, M, F>(
+    state: &T,
+    max_time: Duration,
+    color: bool,
+    table: &TranspositionTable<M>,
+    mut search_depth: F,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + PartialEq + Copy + StateHash,
+    M: Default + Copy + PartialEq,
+    F: FnMut(isize, Option<isize>, &mut SearchContext) -> Option<isize>,
+{
+    let start = Instant::now();
+    let moves = state.child_states(color);
+    if moves.len() == 1 {
+        return forced_move(state, moves[0], start);
+    }
+
+    table.new_search();
+    let mut context = SearchContext::with_stop_token(StopToken::with_deadline(start + max_time));
+    let mut best_move = moves[0];
+    let mut best_score = match color {
+        true => isize::MIN + 1,
+        false => isize::MAX,
+    };
+    let mut depth = 1;
+    let mut completed_depth = 0;
+    while !context.check_stop() {
+        let previous = match completed_depth {
+            0 => None,
+            _ => Some(best_score),
+        };
+        let Some(score) = search_depth(depth, previous, &mut context) else {
+            break;
+        };
+        if let Some(stored_move) = table.stored_move(state, color) {
+            best_move = stored_move;
+        }
+        best_score = score;
+        completed_depth = depth;
+        if mate_within(best_score, depth) {
+            break;
+        }
         depth += 1;
     }
-    println!("Thinking {}ply deep", depth - 1);
-    best_move
+    let mut result = search_result(
+        best_move,
+        best_score,
+        completed_depth,
+        context.nodes(),
+        start,
+    );
+    result.pv = principal_variation(state, best_move, color, table);
+    result
 }
 pub fn find_best_move_t<T, M>(
     state: &T,
     max_time: Duration,
     color: bool,
     search_function: TimedSearchFunction<T>,
-) -> M
+) -> SearchResult<M>
 where
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq,
     M: Copy,
 {
+    let start = Instant::now();
     let moves = state.child_states(color);
     if moves.len() == 1 {
-        return moves[0];
+        return forced_move(state, moves[0], start);
     }
     let mut best_score = match color {
         true => isize::MIN + 1,
@@ -182,29 +592,42 @@ where
     };
     let mut best_move = moves[0];
 
+    let mut context = SearchContext::new();
     let total_time = max_time / moves.len() as u32;
     for next_move in moves {
-        let score = search_function(&(*state + next_move), total_time, !color);
+        let child = *state + next_move;
+        // Not even depth 1 of the move finished in its time
+        let Some(score) = search_function(&child, total_time, !color, &mut context) else {
+            continue;
+        };
+        let score = add_plies(score, 1);
         if color && (score > best_score) || !color && (score < best_score) {
             best_move = next_move;
             best_score = score;
         }
     }
-    best_move
+    search_result(
+        best_move,
+        best_score,
+        context.depth(),
+        context.nodes(),
+        start,
+    )
 }
 pub fn find_best_move<M, T>(
     state: &T,
     depth: isize,
     color: bool,
     search_function: DepthSearchFunction<T>,
-) -> M
+) -> SearchResult<M>
 where
     M: Copy,
     T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq,
 {
+    let start = Instant::now();
     let moves = state.child_states(color);
     if moves.len() == 1 {
-        return moves[0];
+        return forced_move(state, moves[0], start);
     }
 
     let mut best_score = match color {
@@ -212,13 +635,294 @@ where
         false => isize::MAX,
     };
     let mut best_move = moves[0];
+    let mut context = SearchContext::new();
     for next_move in moves {
         let child_state = *state + next_move;
-        let score = search_function(&child_state, depth, !color);
+        // A move the search gave up on has no score, it can't be the best one
+        let Some(score) = search_function(&child_state, depth, !color, &mut context) else {
+            continue;
+        };
+        let score = add_plies(score, 1);
         if color && (score > best_score) || !color && (score < best_score) {
             best_move = next_move;
             best_score = score;
         }
     }
-    best_move
+    search_result(best_move, best_score, depth, context.nodes(), start)
+}
+/// The perfect move by `tablebase`, `None` when it doesn't know the state or one of its children.
+/// Wins are taken the shortest way and losses take the longest.
+pub fn find_best_move_tablebase<T, M, B>(
+    state: &T,
+    color: bool,
+    tablebase: &B,
+) -> Option<SearchResult<M>>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+    M: Copy,
+    B: EndgameTablebase<T>,
+{
+    let start = Instant::now();
+    tablebase.probe(state, color)?;
+    let mut best: Option<(M, isize)> = None;
+    for next_move in state.child_states(color) {
+        let child = *state + next_move;
+        // From the view of `color`
+        let score = match child.is_terminal() {
+            true => static_score(&child, color, 1, isize::MIN + 1, isize::MAX),
+            false => add_plies(-tablebase.probe(&child, !color)?, 1),
+        };
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((next_move, score));
+        }
+    }
+    let (best_move, best_score) = best?;
+    let best_score = match color {
+        true => best_score,
+        false => -best_score,
+    };
+    let distance = mate_distance(best_score).map_or(0, isize::abs);
+    Some(search_result(best_move, best_score, distance, 0, start))
+}
+
+/// `find_best_move` for games with chance nodes, `search_function` is one of the searches of
+/// `expectimax` and gets a window so Star1 and Star2 can prune at the root too
+pub fn find_best_move_chance<M, C, T>(
+    state: &T,
+    depth: isize,
+    color: bool,
+    search_function: ChanceSearchFunction<T>,
+) -> SearchResult<M>
+where
+    M: Copy,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    let start = Instant::now();
+    let moves = state.child_states(color);
+    if moves.len() == 1 {
+        return forced_move(state, moves[0], start);
+    }
+    let mut context = SearchContext::new();
+    let (best_move, best_score) =
+        chance_root(state, depth, color, &moves, &mut context, search_function)
+            .expect("nothing stops a search without a stop token");
+    search_result(best_move, best_score, depth, context.nodes(), start)
+}
+
+/// `find_best_move_t` for games with chance nodes, deepens the root until the time is up
+pub fn find_best_move_chance_t<M, C, T>(
+    state: &T,
+    max_time: Duration,
+    color: bool,
+    search_function: ChanceSearchFunction<T>,
+) -> SearchResult<M>
+where
+    M: Copy + PartialEq,
+    T: ScoreOfState + TerminalState + ChildStates<M> + ChanceStates<C> + Copy,
+{
+    let start = Instant::now();
+    let mut moves = state.child_states(color);
+    if moves.len() == 1 {
+        return forced_move(state, moves[0], start);
+    }
+
+    let mut context = SearchContext::with_stop_token(StopToken::with_deadline(start + max_time));
+    let mut best_move = moves[0];
+    let mut best_score = 0;
+    let mut depth = 1;
+    let mut completed_depth = 0;
+    while !context.check_stop() {
+        let Some((iteration_move, iteration_score)) =
+            chance_root(state, depth, color, &moves, &mut context, search_function)
+        else {
+            break;
+        };
+        best_move = iteration_move;
+        best_score = iteration_score;
+        completed_depth = depth;
+
+        let index = moves.iter().position(|&m| m == best_move).unwrap();
+        moves[..=index].rotate_right(1);
+        depth += 1;
+    }
+    search_result(
+        best_move,
+        best_score,
+        completed_depth,
+        context.nodes(),
+        start,
+    )
+}
+
+/// Searches the root moves with a window that narrows on the best one, the score is from the
+/// view of `true` like the other move finders. `None` when the context stopped the search.
+fn chance_root<M, T>(
+    state: &T,
+    depth: isize,
+    color: bool,
+    moves: &[M],
+    context: &mut SearchContext,
+    search_function: ChanceSearchFunction<T>,
+) -> Option<(M, isize)>
+where
+    M: Copy,
+    T: ChildStates<M> + Copy,
+{
+    let mut best_move = moves[0];
+    let mut alpha = isize::MIN + 1;
+    for &next_move in moves {
+        let child = *state + next_move;
+        // One wider than the negated alpha, adding the ply can lift a loss by one
+        let beta = (-alpha).saturating_add(1);
+        let score = add_plies(
+            -search_function(&child, depth - 1, !color, context, isize::MIN + 1, beta)?,
+            1,
+        );
+        if score > alpha {
+            best_move = next_move;
+            alpha = score;
+        }
+    }
+    Some(match color {
+        true => (best_move, alpha),
+        false => (best_move, -alpha),
+    })
+}
+/// Plays the move `max_n` picks for `player`, the score is the one of `player`
+pub fn find_best_move_max_n<M, T>(state: &T, depth: isize, player: Player) -> SearchResult<M>
+where
+    M: Copy,
+    T: MultiPlayerStates<M> + ScoresOfState + TerminalState + Copy,
+{
+    let start = Instant::now();
+    let moves = state.player_moves(player);
+    if moves.len() == 1 {
+        let score = (*state + moves[0]).scores_of()[player.0];
+        return SearchResult::forced(moves[0], score, start.elapsed());
+    }
+
+    let mut context = SearchContext::new();
+    let next_player = state.next_player(player);
+    let mut best_move = moves[0];
+    let mut best_score = isize::MIN + 1;
+    for next_move in moves {
+        let scores = max_n(&(*state + next_move), depth - 1, next_player, &mut context)
+            .expect("nothing stops a search without a stop token");
+        if scores[player.0] > best_score {
+            best_move = next_move;
+            best_score = scores[player.0];
+        }
+    }
+    search_result(best_move, best_score, depth, context.nodes(), start)
+}
+
+/// Plays the move `paranoid` picks for `player`, the score is the one of `player`
+pub fn find_best_move_paranoid<M, T>(state: &T, depth: isize, player: Player) -> SearchResult<M>
+where
+    M: Copy,
+    T: MultiPlayerStates<M> + ScoresOfState + TerminalState + Copy,
+{
+    let start = Instant::now();
+    let moves = state.player_moves(player);
+    if moves.len() == 1 {
+        let score = (*state + moves[0]).scores_of()[player.0];
+        return SearchResult::forced(moves[0], score, start.elapsed());
+    }
+
+    let mut context = SearchContext::new();
+    let next_player = state.next_player(player);
+    let mut best_move = moves[0];
+    let mut best_score = isize::MIN + 1;
+    for next_move in moves {
+        let child = *state + next_move;
+        let score = paranoid(
+            &child,
+            depth - 1,
+            next_player,
+            player,
+            &mut context,
+            best_score,
+            isize::MAX,
+        )
+        .expect("nothing stops a search without a stop token");
+        if score > best_score {
+            best_move = next_move;
+            best_score = score;
+        }
+    }
+    search_result(best_move, best_score, depth, context.nodes(), start)
+}
+
+pub fn find_best_move_mcts<T, M>(
+    state: &T,
+    budget: SearchBudget,
+    color: bool,
+    tree: &mut MonteCarloTree<M, T>,
+) -> SearchResult<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + PartialEq,
+    M: Copy + PartialEq,
+{
+    let start = Instant::now();
+    let moves = state.child_states(color);
+    if moves.len() == 1 {
+        return forced_move(state, moves[0], start);
+    }
+    let before = tree.playouts();
+    let best_move = tree.search(state, color, budget);
+    SearchResult {
+        best_move,
+        score: (*state + best_move).score_of(),
+        depth: 0,
+        nodes: tree.playouts().saturating_sub(before),
+        elapsed: start.elapsed(),
+        pv: vec![best_move],
+    }
+}
+
+fn forced_move<T, M>(state: &T, only_move: M, start: Instant) -> SearchResult<M>
+where
+    T: ScoreOfState + ChildStates<M> + Copy,
+    M: Copy,
+{
+    let score = add_plies((*state + only_move).score_of(), 1);
+    SearchResult::forced(only_move, score, start.elapsed())
+}
+
+/// A win or loss the search can already see to the end won't change at a bigger depth
+fn mate_within(score: isize, depth: isize) -> bool {
+    mate_distance(score).is_some_and(|plies| plies.abs() <= depth)
+}
+
+/// `best_move` followed by the line stored in the table
+fn principal_variation<T, M>(
+    state: &T,
+    best_move: M,
+    color: bool,
+    table: &TranspositionTable<M>,
+) -> Vec<M>
+where
+    T: ChildStates<M> + StateHash + TerminalState + Copy,
+    M: Default + Copy + PartialEq,
+{
+    let mut pv = vec![best_move];
+    pv.extend(table.principal_variation(&(*state + best_move), !color));
+    pv
+}
+
+fn search_result<M: Copy>(
+    best_move: M,
+    score: isize,
+    depth: isize,
+    nodes: usize,
+    start: Instant,
+) -> SearchResult<M> {
+    SearchResult {
+        best_move,
+        score,
+        depth,
+        nodes,
+        elapsed: start.elapsed(),
+        pv: vec![best_move],
+    }
 }
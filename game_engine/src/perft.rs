@@ -0,0 +1,44 @@
+use super::traits::{ChildStates, TerminalState};
+
+/// The number of move sequences of exactly `depth` plies from `state` with `color` to move.
+/// Games that end before `depth` don't count, like mates in chess perft.
+pub fn perft<T, M>(state: &T, depth: usize, color: bool) -> u64
+where
+    T: ChildStates<M> + TerminalState + Copy,
+    M: Copy,
+{
+    if depth == 0 {
+        return 1;
+    }
+    if state.is_terminal() {
+        return 0;
+    }
+    let moves = state.child_states(color);
+    // The children of the last ply only have to be counted, not made
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|next_move| perft(&(*state + next_move), depth - 1, !color))
+        .sum()
+}
+
+/// `perft` split by root move, to find the move under which two counts differ
+pub fn perft_divide<T, M>(state: &T, depth: usize, color: bool) -> Vec<(M, u64)>
+where
+    T: ChildStates<M> + TerminalState + Copy,
+    M: Copy,
+{
+    if depth == 0 || state.is_terminal() {
+        return Vec::new();
+    }
+    state
+        .child_states(color)
+        .into_iter()
+        .map(|next_move| {
+            let count = perft(&(*state + next_move), depth - 1, !color);
+            (next_move, count)
+        })
+        .collect()
+}
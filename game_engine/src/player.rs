@@ -0,0 +1,65 @@
+use std::ops::Add;
+
+use super::traits::{ChildStates, MultiPlayerStates, ScoreOfState, ScoresOfState, TerminalState};
+
+/// The index of a player in turn order
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct Player(pub usize);
+
+impl Player {
+    /// The player after this one, out of `players`
+    pub const fn next(self, players: usize) -> Self {
+        Player((self.0 + 1) % players)
+    }
+    /// The `color` of this player in a two player game
+    pub const fn color(self) -> bool {
+        self.0 == 0
+    }
+}
+
+/// `true` is the first player
+impl From<bool> for Player {
+    fn from(color: bool) -> Self {
+        match color {
+            true => Player(0),
+            false => Player(1),
+        }
+    }
+}
+
+/// Lets a two player game use the N player searches. The score of `true` is `score_of` and the
+/// score of `false` is its negation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TwoPlayer<T>(pub T);
+
+impl<M, T> Add<M> for TwoPlayer<T>
+where
+    T: Add<M, Output = T>,
+{
+    type Output = Self;
+    fn add(self, new_move: M) -> Self::Output {
+        TwoPlayer(self.0 + new_move)
+    }
+}
+
+impl<M, T: ChildStates<M>> MultiPlayerStates<M> for TwoPlayer<T> {
+    fn players(&self) -> usize {
+        2
+    }
+    fn player_moves(&self, player: Player) -> Vec<M> {
+        self.0.child_states(player.color())
+    }
+}
+
+impl<T: ScoreOfState> ScoresOfState for TwoPlayer<T> {
+    fn scores_of(&self) -> Vec<isize> {
+        let score = self.0.score_of();
+        vec![score, -score]
+    }
+}
+
+impl<T: TerminalState> TerminalState for TwoPlayer<T> {
+    fn is_terminal(&self) -> bool {
+        self.0.is_terminal()
+    }
+}
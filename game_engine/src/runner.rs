@@ -0,0 +1,159 @@
+use std::time::{Duration, Instant};
+
+use super::{
+    agents::Agent,
+    clock::Clock,
+    enhancements::search_result::SearchResult,
+    traits::{ChildStates, ScoreOfState, TerminalState},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GameConfig {
+    /// The clock of `true` and the clock of `false` at the start of the game
+    pub clocks: [Clock; 2],
+    /// The game is a draw after this many moves of both sides together
+    pub max_moves: usize,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            clocks: [Clock::per_move(Duration::from_millis(100)); 2],
+            max_moves: 500,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    /// The color that won
+    Win(bool),
+    Draw,
+}
+
+/// Why the game stopped
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Termination {
+    Terminal,
+    /// The side to move had no moves in a state that isn't terminal and lost, like in the searches
+    NoMoves,
+    MoveLimit,
+    /// The side that moved last ran out of time and lost
+    Timeout,
+}
+
+#[derive(Clone, Debug)]
+pub struct MoveRecord<M> {
+    pub played: M,
+    pub color: bool,
+    pub elapsed: Duration,
+    /// The clock of `color` after the move
+    pub clock: Clock,
+    /// What the agent searched for this move, its score is the eval from the view of `true`
+    pub search: Option<SearchResult<M>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GameOutcome<M> {
+    pub result: GameResult,
+    pub termination: Termination,
+    pub moves: Vec<MoveRecord<M>>,
+}
+
+impl<M> GameOutcome<M> {
+    pub fn winner(&self) -> Option<bool> {
+        match self.result {
+            GameResult::Win(color) => Some(color),
+            GameResult::Draw => None,
+        }
+    }
+    /// Total time used by `color`
+    pub fn time_of(&self, color: bool) -> Duration {
+        self.moves
+            .iter()
+            .filter(|record| record.color == color)
+            .map(|record| record.elapsed)
+            .sum()
+    }
+}
+
+/// Plays `start` to the end with `agents[0]` as `true`, which moves first, and `agents[1]` as
+/// `false`
+pub fn play_game<T, M>(
+    start: &T,
+    agents: [&mut dyn Agent<T, M>; 2],
+    config: &GameConfig,
+) -> GameOutcome<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+    M: Copy,
+{
+    play_game_observed(start, agents, config, |_, _| ())
+}
+
+/// `play_game` that calls `observer` with the new state after every move
+pub fn play_game_observed<T, M, F>(
+    start: &T,
+    mut agents: [&mut dyn Agent<T, M>; 2],
+    config: &GameConfig,
+    mut observer: F,
+) -> GameOutcome<M>
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy,
+    M: Copy,
+    F: FnMut(&T, &MoveRecord<M>),
+{
+    let mut state = *start;
+    let mut clocks = config.clocks;
+    let mut color = true;
+    let mut moves = Vec::new();
+    let termination = loop {
+        if state.is_terminal() {
+            break Termination::Terminal;
+        }
+        if moves.len() >= config.max_moves {
+            break Termination::MoveLimit;
+        }
+        if state.child_states(color).is_empty() {
+            break Termination::NoMoves;
+        }
+
+        let (agent, clock) = match color {
+            true => (&mut agents[0], &mut clocks[0]),
+            false => (&mut agents[1], &mut clocks[1]),
+        };
+        let start_time = Instant::now();
+        let played = agent.choose(&state, color, clock);
+        let elapsed = start_time.elapsed();
+        let in_time = clock.punch(elapsed);
+        let record = MoveRecord {
+            played,
+            color,
+            elapsed,
+            clock: *clock,
+            search: agent.last_search().cloned(),
+        };
+        state = state + played;
+        observer(&state, &record);
+        moves.push(record);
+        if !in_time {
+            break Termination::Timeout;
+        }
+        color = !color;
+    };
+
+    let result = match termination {
+        Termination::MoveLimit => GameResult::Draw,
+        Termination::Timeout | Termination::NoMoves => GameResult::Win(!color),
+        Termination::Terminal => match state.score_of() {
+            score if score > 0 => GameResult::Win(true),
+            score if score < 0 => GameResult::Win(false),
+            _ => GameResult::Draw,
+        },
+    };
+    GameOutcome {
+        result,
+        termination,
+        moves,
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread::available_parallelism,
+};
+
+use crossbeam::thread;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::{
+    agents::Agent,
+    clock::Clock,
+    runner::{play_game, GameConfig},
+    traits::{ChildStates, ScoreOfState, TerminalState},
+};
+
+/// Two sided 95% confidence
+const Z_95: f64 = 1.96;
+
+/// A named agent with its own clock. Every game gets a fresh agent from `new_agent`, so games
+/// can run in parallel and don't share tables.
+pub struct Engine<'a, T, M> {
+    pub name: String,
+    pub clock: Clock,
+    new_agent: Box<dyn Fn() -> Box<dyn Agent<T, M> + 'a> + Send + Sync + 'a>,
+}
+
+impl<'a, T, M> Engine<'a, T, M> {
+    pub fn new<F>(name: &str, clock: Clock, new_agent: F) -> Self
+    where
+        F: Fn() -> Box<dyn Agent<T, M> + 'a> + Send + Sync + 'a,
+    {
+        Self {
+            name: String::from(name),
+            clock,
+            new_agent: Box::new(new_agent),
+        }
+    }
+}
+
+/// Who plays who
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Schedule {
+    /// Every engine against every other engine
+    RoundRobin,
+    /// The first engine against every other engine
+    Gauntlet,
+}
+
+/// Sequential probability ratio test of H0: elo = `elo0` against H1: elo = `elo1`, with error
+/// rates `alpha` and `beta`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SprtResult {
+    /// Log likelihood ratio, H1 is accepted above `upper` and H0 below `lower`
+    pub llr: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl SprtResult {
+    /// `Some(true)` when H1 is accepted, `Some(false)` when H0 is, `None` while undecided
+    pub fn decision(&self) -> Option<bool> {
+        if self.llr >= self.upper {
+            Some(true)
+        } else if self.llr <= self.lower {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TournamentConfig {
+    pub schedule: Schedule,
+    /// Games of every pairing, both engines get each colour in half of them
+    pub games_per_pairing: usize,
+    pub max_moves: usize,
+    /// Random moves of both sides before the engines take over, so the games differ. Both games
+    /// of a colour swapped pair start from the same opening.
+    pub opening_moves: usize,
+    pub seed: u64,
+    pub threads: usize,
+    /// Stops a pairing as soon as the test is decided
+    pub sprt: Option<Sprt>,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            schedule: Schedule::RoundRobin,
+            games_per_pairing: 20,
+            max_moves: 500,
+            opening_moves: 2,
+            seed: 42,
+            threads: available_parallelism().map_or(1, |threads| threads.get()),
+            sprt: None,
+        }
+    }
+}
+
+/// The games between two engines, counted from the view of `first`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Pairing {
+    pub first: usize,
+    pub second: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Pairing {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+    /// Points per game of `first`, a draw is half a point and no games count as even
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+    /// Elo difference of `first` over `second` and the half width of its 95% confidence interval
+    pub fn elo(&self) -> (f64, f64) {
+        if self.games() == 0 {
+            return (0.0, f64::INFINITY);
+        }
+        let score = self.score();
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+        let lower = elo_of(score - Z_95 * deviation);
+        let upper = elo_of(score + Z_95 * deviation);
+        (elo_of(score), (upper - lower) / 2.0)
+    }
+    /// Uses the normal approximation of the log likelihood ratio on the score per game
+    pub fn sprt(&self, sprt: &Sprt) -> SprtResult {
+        let lower = (sprt.beta / (1.0 - sprt.alpha)).ln();
+        let upper = ((1.0 - sprt.beta) / sprt.alpha).ln();
+        let variance = self.variance();
+        let llr = match variance > 0.0 {
+            true => {
+                let score0 = score_of_elo(sprt.elo0);
+                let score1 = score_of_elo(sprt.elo1);
+                (score1 - score0) * (2.0 * self.score() - score0 - score1) / (2.0 * variance)
+                    * self.games() as f64
+            }
+            false => 0.0,
+        };
+        SprtResult { llr, lower, upper }
+    }
+    fn variance(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let score = self.score();
+        let games = self.games() as f64;
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+    fn record(&mut self, winner: Option<bool>, first_color: bool) {
+        match winner {
+            Some(color) if color == first_color => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+pub struct TournamentReport {
+    pub names: Vec<String>,
+    pub pairings: Vec<Pairing>,
+    pub sprt: Option<Sprt>,
+}
+
+impl Display for TournamentReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for pairing in &self.pairings {
+            let (elo, error) = pairing.elo();
+            writeln!(
+                f,
+                "{} vs {}: +{} ={} -{} ({} games), elo {:+.1} +- {:.1}",
+                self.names[pairing.first],
+                self.names[pairing.second],
+                pairing.wins,
+                pairing.draws,
+                pairing.losses,
+                pairing.games(),
+                elo,
+                error
+            )?;
+            if let Some(sprt) = &self.sprt {
+                let result = pairing.sprt(sprt);
+                let decision = match result.decision() {
+                    Some(true) => "H1 accepted",
+                    Some(false) => "H0 accepted",
+                    None => "undecided",
+                };
+                writeln!(
+                    f,
+                    "\tsprt [{}, {}]: llr {:.2} ({:.2}, {:.2}) {}",
+                    sprt.elo0, sprt.elo1, result.llr, result.lower, result.upper, decision
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Plays every pairing of `config.schedule` from `start` on `config.threads` threads
+pub fn run_tournament<T, M>(
+    start: &T,
+    engines: &[Engine<T, M>],
+    config: &TournamentConfig,
+) -> TournamentReport
+where
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + Sync,
+    M: Copy,
+{
+    let pairings: Vec<(usize, usize)> = match config.schedule {
+        Schedule::RoundRobin => (0..engines.len())
+            .flat_map(|first| (first + 1..engines.len()).map(move |second| (first, second)))
+            .collect(),
+        Schedule::Gauntlet => (1..engines.len()).map(|second| (0, second)).collect(),
+    };
+    // Games of different pairings are interleaved, so every pairing makes progress
+    let games: Vec<(usize, usize)> = (0..config.games_per_pairing)
+        .flat_map(|game| (0..pairings.len()).map(move |pairing| (pairing, game)))
+        .collect();
+    let results: Vec<Mutex<Pairing>> = pairings
+        .iter()
+        .map(|&(first, second)| {
+            Mutex::new(Pairing {
+                first,
+                second,
+                ..Pairing::default()
+            })
+        })
+        .collect();
+    let decided: Vec<AtomicBool> = pairings.iter().map(|_| AtomicBool::new(false)).collect();
+    let next_game = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..config.threads.max(1) {
+            scope.spawn(|_| loop {
+                let index = next_game.fetch_add(1, Ordering::Relaxed);
+                let Some(&(pairing, game)) = games.get(index) else {
+                    break;
+                };
+                if decided[pairing].load(Ordering::Relaxed) {
+                    continue;
+                }
+                let (first, second) = pairings[pairing];
+                let seed = config.seed ^ ((pairing as u64) << 32) ^ (game / 2) as u64;
+                let opening = random_opening(start, config.opening_moves, seed);
+
+                // ! ============ Alternate the colours ============
+                let first_color = game % 2 == 0;
+                let (white, black) = match first_color {
+                    true => (&engines[first], &engines[second]),
+                    false => (&engines[second], &engines[first]),
+                };
+                let mut white_agent = (white.new_agent)();
+                let mut black_agent = (black.new_agent)();
+                let game_config = GameConfig {
+                    clocks: [white.clock, black.clock],
+                    max_moves: config.max_moves,
+                };
+                let outcome = play_game(
+                    &opening,
+                    [white_agent.as_mut(), black_agent.as_mut()],
+                    &game_config,
+                );
+
+                let mut result = results[pairing].lock().unwrap();
+                result.record(outcome.winner(), first_color);
+                if let Some(sprt) = &config.sprt {
+                    if result.sprt(sprt).decision().is_some() {
+                        decided[pairing].store(true, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    TournamentReport {
+        names: engines.iter().map(|engine| engine.name.clone()).collect(),
+        pairings: results
+            .into_iter()
+            .map(|result| result.into_inner().unwrap())
+            .collect(),
+        sprt: config.sprt,
+    }
+}
+
+/// `moves` random moves for both sides, so `true` is to move again afterwards
+fn random_opening<T, M>(start: &T, moves: usize, seed: u64) -> T
+where
+    T: TerminalState + ChildStates<M> + Copy,
+    M: Copy,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut state = *start;
+    for ply in 0..moves * 2 {
+        if state.is_terminal() {
+            break;
+        }
+        match state.child_states(ply % 2 == 0).choose(&mut rng) {
+            Some(&next_move) => state = state + next_move,
+            None => break,
+        }
+    }
+    state
+}
+
+fn score_of_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// Scores of 0 and 1 are clamped, which caps the difference at about 1200 Elo
+fn elo_of(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    -400.0 * (1.0 / score - 1.0).log10() + 0.0
+}
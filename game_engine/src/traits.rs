@@ -1,5 +1,7 @@
 use std::ops::{Add, Sub};
 
+use super::{enhancements::mate_score::WIN, player::Player};
+
 pub trait ChildStates<T>
 where
     Self: Add<T, Output = Self> + Sub<T, Output = Self>,
@@ -12,6 +14,66 @@ pub trait ScoreOfState {
 pub trait TerminalState {
     fn is_terminal(&self) -> bool;
 }
+/// `ChildStates` for games with any number of players
+pub trait MultiPlayerStates<T>
+where
+    Self: Add<T, Output = Self>,
+{
+    fn players(&self) -> usize;
+    fn player_moves(&self, player: Player) -> Vec<T>;
+    /// Who moves after `player`, the next one in turn order unless the game skips players
+    fn next_player(&self, player: Player) -> Player {
+        player.next(self.players())
+    }
+}
+/// `ScoreOfState` for games with any number of players, one score per player where higher is
+/// better for that player
+pub trait ScoresOfState {
+    fn scores_of(&self) -> Vec<isize>;
+}
+/// Games where chance decides what happens in some states, like the roll of a die
+pub trait ChanceStates<C>
+where
+    Self: Add<C, Output = Self>,
+{
+    /// Whether a chance event happens before `color` gets to move
+    fn is_chance(&self, color: bool) -> bool;
+    /// Every outcome with its weight, the probability of an outcome is its share of the total.
+    /// Outcomes with weight 0 never happen, a state where they all weigh 0 is searched as if no
+    /// chance event happened.
+    fn chance_outcomes(&self, color: bool) -> Vec<(C, u32)>;
+    /// Lowest and highest `score_of`, Star1 and Star2 prune on them so tighter is better
+    fn score_bounds(&self) -> (isize, isize) {
+        (-WIN, WIN)
+    }
+}
+/// Games that can list the moves that change the score a lot, for the quiescence search
+pub trait NoisyMoves<M> {
+    /// A subset of `child_states`, every sequence of noisy moves has to end
+    fn noisy_moves(&self, color: bool) -> Vec<M>;
+}
+/// Games where passing the turn is a sound test for null move pruning
+pub trait NullMove {
+    /// False in zugzwang like states, where passing would be better than any real move
+    fn can_pass(&self, color: bool) -> bool;
+}
+/// Exact results for some states of `T`, like the states of an endgame tablebase
+pub trait EndgameTablebase<T> {
+    /// The score from the view of `color` as a mate score counted from `state`, `None` for
+    /// states the table doesn't know
+    fn probe(&self, state: &T, color: bool) -> Option<isize>;
+}
 pub trait StateHash {
     fn hash(&self, color: bool) -> isize;
+    /// A second hash, independent of `hash`, used to tell apart states whose `hash` collides
+    fn verification(&self, _color: bool) -> u32 {
+        0
+    }
+}
+/// A fixed size binary form, used to write moves to disk
+pub trait ByteEncoding: Sized {
+    const ENCODED_SIZE: usize;
+    fn encode(&self) -> Vec<u8>;
+    /// `None` when the bytes don't describe a valid value
+    fn decode(bytes: &[u8]) -> Option<Self>;
 }
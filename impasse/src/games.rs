@@ -1,2 +1,3 @@
 pub mod impasse;
+pub mod three_player_tic_tac_toe;
 pub mod tic_tac_toe;
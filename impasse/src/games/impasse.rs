@@ -1,6 +1,8 @@
 pub mod actions;
 pub mod game;
 pub mod move_type;
+pub mod perft;
 pub mod position;
+pub mod tablebase;
 
 pub type GameField = [[isize; 8]; 8];
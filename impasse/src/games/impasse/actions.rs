@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use game_engine::traits::ByteEncoding;
+
 use super::{move_type::MoveType, position::Position};
 
 #[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord)]
@@ -31,7 +33,7 @@ impl Move {
     pub fn is_valid(&self) -> bool {
         self.move_type != MoveType::Invalid
     }
-    pub fn to_bear_off(&mut self) {
+    pub fn set_bear_off(&mut self) {
         self.move_type = self.move_type + MoveType::BearOff;
         if self.positions[0].new_sign.abs() == 2 {
             self.positions[0].new_sign /= 2
@@ -42,3 +44,44 @@ impl Move {
         }
     }
 }
+
+/// The move type followed by x, y, old sign and new sign of every position
+impl ByteEncoding for Move {
+    const ENCODED_SIZE: usize = 13;
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![self.move_type as u8];
+        for pos in self.positions {
+            bytes.extend([
+                pos.x as u8,
+                pos.y as u8,
+                pos.old_sign as i8 as u8,
+                pos.new_sign as i8 as u8,
+            ]);
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::ENCODED_SIZE {
+            return None;
+        }
+        let move_type = *MoveType::ALL.get(bytes[0] as usize)?;
+        let mut positions = [Position::default(); 3];
+        for (pos, chunk) in positions.iter_mut().zip(bytes[1..].chunks(4)) {
+            if chunk[0] >= 8 || chunk[1] >= 8 {
+                return None;
+            }
+            *pos = Position {
+                x: chunk[0] as usize,
+                y: chunk[1] as usize,
+                old_sign: chunk[2] as i8 as isize,
+                new_sign: chunk[3] as i8 as isize,
+            };
+        }
+        Some(Move {
+            positions,
+            move_type,
+        })
+    }
+}
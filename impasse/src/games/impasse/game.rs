@@ -1,8 +1,11 @@
-use std::{fmt::Display, isize, ops};
+use std::{fmt::Display, ops};
 
 use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use game_engine::traits::{ChildStates, ScoreOfState, StateHash, TerminalState};
+use game_engine::enhancements::mate_score::WIN;
+use game_engine::traits::{
+    ChildStates, NoisyMoves, NullMove, ScoreOfState, StateHash, TerminalState,
+};
 type TableSize = u64;
 type HashField = [[[TableSize; 4]; 8]; 8];
 
@@ -69,9 +72,9 @@ impl ScoreOfState for Impasse<'_> {
             }
         }
         if pos_pieces == 0 {
-            return isize::MAX;
+            return WIN;
         } else if neg_pieces == 0 {
-            return isize::MIN + 1;
+            return -WIN;
         }
         score as isize + (neg_pieces - pos_pieces) * 10
     }
@@ -103,6 +106,41 @@ impl ChildStates<Move> for Impasse<'_> {
     }
 }
 
+impl NoisyMoves<Move> for Impasse<'_> {
+    /// Crowning and bearing off, each can only happen a limited number of times
+    fn noisy_moves(&self, color: bool) -> Vec<Move> {
+        let mut moves = self.child_states(color);
+        moves.retain(|new_move| {
+            matches!(
+                new_move.move_type,
+                MoveType::Crown
+                    | MoveType::BearOff
+                    | MoveType::TransposeBearOff
+                    | MoveType::TransposeCrown
+                    | MoveType::BearOffCrown
+                    | MoveType::ImpasseCrown
+            )
+        });
+        moves
+    }
+}
+
+impl NullMove for Impasse<'_> {
+    /// Without a normal move the side to move has to remove a piece, which passing would skip
+    fn can_pass(&self, color: bool) -> bool {
+        let mut possible_moves = Vec::new();
+        for i in 0..8 {
+            for j in 0..8 {
+                self.move_normal(&mut possible_moves, color, i, j);
+                if !possible_moves.is_empty() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
 impl Display for Impasse<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for i in 0..8 {
@@ -305,7 +343,7 @@ impl Impasse<'_> {
             // BearOff
             if new_field.can_bear_off(x, y) {
                 new_field = new_field - new_move;
-                new_move.to_bear_off();
+                new_move.set_bear_off();
                 new_field = new_field + new_move;
             }
 
@@ -354,7 +392,7 @@ impl Impasse<'_> {
                     // BearOff
                     if new_field.can_bear_off(x, y) || new_field.can_bear_off(new_x, new_y) {
                         new_field = new_field - new_move;
-                        new_move.to_bear_off();
+                        new_move.set_bear_off();
                         new_field = new_field + new_move;
                     }
 
@@ -467,6 +505,16 @@ impl StateHash for Impasse<'_> {
         hash_val ^= color as TableSize;
         hash_val as isize
     }
+    /// A plain polynomial hash of the field, shares nothing with the Zobrist hash
+    fn verification(&self, color: bool) -> u32 {
+        let mut check = color as u32;
+        for row in self.game_field {
+            for field in row {
+                check = check.wrapping_mul(31).wrapping_add((field + 3) as u32);
+            }
+        }
+        check
+    }
 }
 
 const fn get_hash(number: isize) -> usize {
@@ -1,7 +1,8 @@
 use std::{fmt::Display, ops};
 
-#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Default)]
 pub enum MoveType {
+    #[default]
     Invalid,
     Normal,
     Transpose,
@@ -13,12 +14,20 @@ pub enum MoveType {
     BearOffCrown,
     ImpasseCrown,
 }
-impl Default for MoveType {
-    fn default() -> Self {
-        Self::Invalid
-    }
-}
 impl MoveType {
+    /// All variants, in the order of their discriminants
+    pub const ALL: [MoveType; 10] = [
+        MoveType::Invalid,
+        MoveType::Normal,
+        MoveType::Transpose,
+        MoveType::Crown,
+        MoveType::TransposeCrown,
+        MoveType::TransposeBearOff,
+        MoveType::BearOff,
+        MoveType::Impasse,
+        MoveType::BearOffCrown,
+        MoveType::ImpasseCrown,
+    ];
     fn string_map(self) -> String {
         match self {
             MoveType::Invalid => String::from("Invalid"),
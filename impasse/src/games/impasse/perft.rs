@@ -0,0 +1,144 @@
+use super::GameField;
+
+/// A state with the number of move sequences of every depth from it, counted with
+/// `game_engine::perft::perft`. A change to the move generation that changes a count has to
+/// update it.
+pub struct PerftReference {
+    pub name: &'static str,
+    /// From the top of the board, `o`/`O` for a single/double of true and `x`/`X` for false
+    pub rows: [&'static str; 8],
+    pub color: bool,
+    /// The count of depth 1 first
+    pub counts: &'static [u64],
+}
+
+impl PerftReference {
+    pub fn game_field(&self) -> GameField {
+        let mut field = [[0; 8]; 8];
+        for (x, row) in self.rows.iter().enumerate() {
+            for (y, square) in row.chars().enumerate() {
+                field[x][y] = match square {
+                    'o' => 1,
+                    'O' => 2,
+                    'x' => -1,
+                    'X' => -2,
+                    _ => 0,
+                };
+            }
+        }
+        field
+    }
+}
+
+pub const REFERENCES: [PerftReference; 3] = [
+    PerftReference {
+        name: "start",
+        rows: [
+            "-O-x-O-x", "x-O-x-O-", "--------", "--------", "--------", "--------", "-X-o-X-o",
+            "o-X-o-X-",
+        ],
+        color: true,
+        counts: &[22, 492, 9692, 193139, 3489530],
+    },
+    // Crowns, transposes into a crown and bears off
+    PerftReference {
+        name: "crowning",
+        rows: [
+            "---O----", "--o-X---", "--------", "------x-", "-----o--", "--------", "-O---x--",
+            "--------",
+        ],
+        color: true,
+        counts: &[6, 40, 375, 2917, 34291, 299742],
+    },
+    // True has no move but an impasse, which crowns the waiting single
+    PerftReference {
+        name: "impasse",
+        rows: [
+            "-o------", "--------", "-----x--", "--------", "--------", "--------", "-O------",
+            "x-X-----",
+        ],
+        color: true,
+        counts: &[2, 23, 98, 1044, 4808, 47660, 178459],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use game_engine::{
+        perft::perft,
+        traits::{ChildStates, TerminalState},
+    };
+
+    use super::{
+        super::{game::Impasse, move_type::MoveType},
+        PerftReference, REFERENCES,
+    };
+
+    /// Beyond this the start position has millions of move sequences, `impasse perft` and the
+    /// ignored test below check those counts
+    const START_DEPTH: usize = 3;
+
+    fn check_counts(reference: &PerftReference, depths: usize) {
+        let hash_field = Impasse::gen_hash_field(1);
+        let mut state = Impasse::new(&hash_field);
+        state.game_field = reference.game_field();
+        for (index, &expected) in reference.counts.iter().take(depths).enumerate() {
+            assert_eq!(
+                perft(&state, index + 1, reference.color),
+                expected,
+                "{} at depth {}",
+                reference.name,
+                index + 1
+            );
+        }
+    }
+
+    #[test]
+    fn move_generation_matches_the_references() {
+        for reference in &REFERENCES {
+            let depths = match reference.name {
+                "start" => START_DEPTH,
+                _ => reference.counts.len(),
+            };
+            check_counts(reference, depths);
+        }
+    }
+
+    #[test]
+    #[ignore = "counts millions of move sequences"]
+    fn move_generation_matches_the_start_reference_at_every_depth() {
+        let start = REFERENCES
+            .iter()
+            .find(|reference| reference.name == "start");
+        let start = start.unwrap();
+        check_counts(start, start.counts.len());
+    }
+
+    fn add_move_types(state: &Impasse, depth: usize, color: bool, seen: &mut Vec<MoveType>) {
+        if depth == 0 || state.is_terminal() {
+            return;
+        }
+        for next_move in state.child_states(color) {
+            if !seen.contains(&next_move.move_type) {
+                seen.push(next_move.move_type);
+            }
+            add_move_types(&(*state + next_move), depth - 1, !color, seen);
+        }
+    }
+
+    #[test]
+    fn references_reach_every_move_type() {
+        let hash_field = Impasse::gen_hash_field(1);
+        let mut seen = Vec::new();
+        for reference in &REFERENCES {
+            let mut state = Impasse::new(&hash_field);
+            state.game_field = reference.game_field();
+            add_move_types(&state, 4, reference.color, &mut seen);
+        }
+        for move_type in MoveType::ALL {
+            if move_type != MoveType::Invalid {
+                assert!(seen.contains(&move_type), "no {} move", move_type);
+            }
+        }
+    }
+}
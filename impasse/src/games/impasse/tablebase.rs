@@ -0,0 +1,333 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use game_engine::enhancements::mate_score::{loss_in, win_in};
+use game_engine::traits::{ChildStates, EndgameTablebase, TerminalState};
+
+use super::game::Impasse;
+use super::GameField;
+
+const MAGIC: &[u8; 4] = b"IMTB";
+const VERSION: u8 = 1;
+/// The dark squares, the only ones a piece can stand on
+const SQUARES: usize = 32;
+/// Pieces per side of the largest tablebase, 3 already takes 3.5 GB
+pub const MAX_PIECES: usize = 3;
+
+/// The result of every state with at most `pieces` pieces per side, a double counts as one piece.
+///
+/// A state is stored as the plies to the end of the game plus one, zero where nothing is stored.
+/// Impasse can't repeat a state and has no draws, so an odd distance is a win for the side to
+/// move and an even one a loss.
+pub struct Tablebase {
+    pieces: usize,
+    /// The number of ways one side can place its pieces
+    side_count: usize,
+    results: Vec<u8>,
+}
+
+impl Tablebase {
+    fn empty(pieces: usize) -> Self {
+        let side_count = side_offset(pieces + 1);
+        Self {
+            pieces,
+            side_count,
+            results: vec![0; side_count * side_count * 2],
+        }
+    }
+    /// Solves every state with at most `pieces` pieces per side. A move never adds a piece and
+    /// always moves a piece closer to being removed, so the states form a graph without cycles
+    /// that is solved depth first from every state.
+    pub fn generate(pieces: usize) -> Self {
+        assert!(
+            (1..=MAX_PIECES).contains(&pieces),
+            "tablebases have 1 to {} pieces per side",
+            MAX_PIECES
+        );
+        let hash_field = [[[0; 4]; 8]; 8];
+        let mut tablebase = Self::empty(pieces);
+        let sides = side_layouts(pieces);
+        for white in &sides {
+            for black in &sides {
+                if white
+                    .iter()
+                    .any(|(square, _)| black.iter().any(|(other_square, _)| square == other_square))
+                {
+                    continue;
+                }
+                let mut state = Impasse {
+                    game_field: [[0; 8]; 8],
+                    hash_field: &hash_field,
+                };
+                for (sign, layout) in [(1, white), (-1, black)] {
+                    for &(square, double) in layout {
+                        let (x, y) = coordinates(square);
+                        state.game_field[x][y] = match double {
+                            true => 2 * sign,
+                            false => sign,
+                        };
+                    }
+                }
+                tablebase.solve(&state, true);
+                tablebase.solve(&state, false);
+            }
+        }
+        tablebase
+    }
+    /// Plies until the end of the game with perfect play
+    fn solve(&mut self, state: &Impasse, color: bool) -> usize {
+        let index = self
+            .index_of(&state.game_field, color)
+            .expect("a move added a piece");
+        if self.results[index] != 0 {
+            return self.results[index] as usize - 1;
+        }
+        let distances: Vec<usize> = state
+            .child_states(color)
+            .into_iter()
+            .map(|next_move| {
+                let child = *state + next_move;
+                // Only the side that moves can lose pieces, so a move that ends the game wins it
+                match child.is_terminal() {
+                    true => 0,
+                    false => self.solve(&child, !color),
+                }
+            })
+            .collect();
+        // The side to move wins by reaching a loss of the other side as soon as it can, and
+        // otherwise loses as late as it can
+        let fastest_win = distances.iter().filter(|&distance| distance % 2 == 0).min();
+        let slowest_loss = distances.iter().max();
+        let distance = fastest_win.or(slowest_loss).expect("a state without moves") + 1;
+        self.results[index] = u8::try_from(distance + 1).expect("game longer than 254 plies");
+        distance
+    }
+    pub fn pieces(&self) -> usize {
+        self.pieces
+    }
+    /// The number of solved states
+    pub fn size(&self) -> usize {
+        self.results.iter().filter(|&&result| result != 0).count()
+    }
+
+    fn index_of(&self, field: &GameField, color: bool) -> Option<usize> {
+        let mut white = Vec::with_capacity(self.pieces);
+        let mut black = Vec::with_capacity(self.pieces);
+        for square in 0..SQUARES {
+            let (x, y) = coordinates(square);
+            let side = match field[x][y] {
+                0 => continue,
+                sign if sign > 0 => &mut white,
+                _ => &mut black,
+            };
+            if side.len() == self.pieces {
+                return None;
+            }
+            side.push((square, field[x][y].abs() == 2));
+        }
+        if white.is_empty() || black.is_empty() {
+            return None;
+        }
+        let index = side_rank(&white) * self.side_count + side_rank(&black);
+        Some(index * 2 + color as usize)
+    }
+
+    // ! ======================= Saving and loading =======================
+    /// Layout: magic, version, pieces per side (u8), then one byte per state
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, self.pieces as u8])?;
+        writer.write_all(&self.results)?;
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut header = [0; 6];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("Not a tablebase file"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid("Unsupported tablebase version"));
+        }
+        let pieces = header[5] as usize;
+        if !(1..=MAX_PIECES).contains(&pieces) {
+            return Err(invalid("Unsupported number of pieces in tablebase"));
+        }
+        // Checked before the results are allocated, a broken file shouldn't take gigabytes
+        let side_count = side_offset(pieces + 1);
+        if length != (header.len() + side_count * side_count * 2) as u64 {
+            return Err(invalid("Tablebase file has the wrong length"));
+        }
+        let mut tablebase = Self::empty(pieces);
+        reader.read_exact(&mut tablebase.results)?;
+        Ok(tablebase)
+    }
+}
+
+impl EndgameTablebase<Impasse<'_>> for Tablebase {
+    /// `None` when the state has too many pieces or is over
+    fn probe(&self, state: &Impasse, color: bool) -> Option<isize> {
+        let distance = match self.results[self.index_of(&state.game_field, color)?] {
+            0 => return None,
+            stored => (stored - 1) as isize,
+        };
+        Some(match distance % 2 {
+            1 => win_in(distance),
+            _ => loss_in(distance),
+        })
+    }
+}
+
+// ! ======================= Indexing =======================
+// One side is indexed by its number of pieces, the set of squares they stand on and which of
+// them are doubles. A state is a pair of sides and the side to move, sides that overlap are
+// never stored.
+
+const fn coordinates(square: usize) -> (usize, usize) {
+    let x = square / 4;
+    (x, 2 * (square % 4) + (x + 1) % 2)
+}
+
+const fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    let mut result = 1;
+    let mut i = 0;
+    while i < k {
+        result = result * (n - i) / (i + 1);
+        i += 1;
+    }
+    result
+}
+
+/// Sides with fewer than `pieces` pieces come first
+const fn side_offset(pieces: usize) -> usize {
+    let mut offset = 0;
+    let mut count = 1;
+    while count < pieces {
+        offset += binomial(SQUARES, count) << count;
+        count += 1;
+    }
+    offset
+}
+
+/// `side` is sorted by square
+fn side_rank(side: &[(usize, bool)]) -> usize {
+    let mut squares = 0;
+    let mut doubles = 0;
+    for (index, &(square, double)) in side.iter().enumerate() {
+        squares += binomial(square, index + 1);
+        doubles |= (double as usize) << index;
+    }
+    side_offset(side.len()) + (squares << side.len()) + doubles
+}
+
+/// Every placement of 1 to `pieces` pieces of one side, sorted by square
+fn side_layouts(pieces: usize) -> Vec<Vec<(usize, bool)>> {
+    let mut layouts = Vec::new();
+    let mut squares = Vec::new();
+    add_layouts(pieces, 0, &mut squares, &mut layouts);
+    layouts
+}
+
+fn add_layouts(
+    pieces: usize,
+    first: usize,
+    squares: &mut Vec<usize>,
+    layouts: &mut Vec<Vec<(usize, bool)>>,
+) {
+    if !squares.is_empty() {
+        for doubles in 0..1 << squares.len() {
+            layouts.push(
+                squares
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &square)| (square, doubles >> index & 1 == 1))
+                    .collect(),
+            );
+        }
+    }
+    if squares.len() == pieces {
+        return;
+    }
+    for square in first..SQUARES {
+        squares.push(square);
+        add_layouts(pieces, square + 1, squares, layouts);
+        squares.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_engine::{
+        enhancements::{
+            base::{nega_with_table_ordered, nega_with_table_tablebase},
+            move_ordering::MoveOrdering,
+            replacement_policy::ReplacementPolicy,
+            search_state::SearchContext,
+            transposition_table::TranspositionTable,
+        },
+        move_finders::find_best_move_tablebase,
+        traits::{EndgameTablebase, ScoreOfState},
+    };
+
+    use super::{coordinates, Impasse, Tablebase};
+
+    #[test]
+    fn searches_probe_only_the_tablebase_they_get() {
+        let tablebase = Tablebase::generate(1);
+        let hash_field = Impasse::gen_hash_field(1);
+        let mut state = Impasse::new(&hash_field);
+        state.game_field = [[0; 8]; 8];
+        for (square, sign) in [(0, 1), (31, -1)] {
+            let (x, y) = coordinates(square);
+            state.game_field[x][y] = sign;
+        }
+        let exact = tablebase
+            .probe(&state, true)
+            .expect("a state with one piece per side");
+        let best = find_best_move_tablebase(&state, true, &tablebase).expect("a solved state");
+        assert_eq!(best.score, exact);
+
+        let table = TranspositionTable::new(1, ReplacementPolicy::AlwaysReplace);
+        let search = |tablebase: Option<&Tablebase>| {
+            let (mut ordering, mut context) = (MoveOrdering::default(), SearchContext::new());
+            let (alpha, beta) = (isize::MIN + 1, isize::MAX);
+            match tablebase {
+                Some(tablebase) => nega_with_table_tablebase(
+                    &state,
+                    0,
+                    true,
+                    &table,
+                    tablebase,
+                    &mut ordering,
+                    &mut context,
+                    alpha,
+                    beta,
+                ),
+                None => nega_with_table_ordered(
+                    &state,
+                    0,
+                    true,
+                    &table,
+                    &mut ordering,
+                    &mut context,
+                    alpha,
+                    beta,
+                ),
+            }
+        };
+        assert_eq!(search(Some(&tablebase)), Some(exact));
+        assert_eq!(search(None), Some(state.score_of()));
+    }
+}
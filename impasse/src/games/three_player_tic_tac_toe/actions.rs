@@ -0,0 +1,8 @@
+use game_engine::player::Player;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub x: usize,
+    pub y: usize,
+    pub player: Player,
+}
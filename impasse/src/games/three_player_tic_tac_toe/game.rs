@@ -0,0 +1,230 @@
+use std::{fmt::Display, ops::Add};
+
+use game_engine::{
+    player::Player,
+    traits::{MultiPlayerStates, ScoresOfState, TerminalState},
+};
+
+use super::actions::Move;
+
+/// Three players take turns on a 4x4 board, the first with three in a row wins
+const SIZE: usize = 4;
+const PLAYERS: usize = 3;
+const WIN_SCORE: isize = 100;
+/// Every straight line of three fields on the board
+const LINES: [[(usize, usize); 3]; 24] = gen_lines();
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct ThreePlayerTicTacToe {
+    /// 0 for an empty field, otherwise the player index plus one
+    pub game_field: [[usize; SIZE]; SIZE],
+}
+
+impl Add<Move> for ThreePlayerTicTacToe {
+    type Output = ThreePlayerTicTacToe;
+
+    fn add(self, rhs: Move) -> Self::Output {
+        let mut board = self;
+        board.game_field[rhs.x][rhs.y] = rhs.player.0 + 1;
+        board
+    }
+}
+
+impl MultiPlayerStates<Move> for ThreePlayerTicTacToe {
+    fn players(&self) -> usize {
+        PLAYERS
+    }
+    fn player_moves(&self, player: Player) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for x in 0..SIZE {
+            for y in 0..SIZE {
+                if self.game_field[x][y] == 0 {
+                    moves.push(Move { x, y, player });
+                }
+            }
+        }
+        moves
+    }
+}
+
+impl ScoresOfState for ThreePlayerTicTacToe {
+    /// The winner gets everything, otherwise every line a player could still finish counts
+    fn scores_of(&self) -> Vec<isize> {
+        if let Some(winner) = self.winner() {
+            let mut scores = vec![-WIN_SCORE / 2; PLAYERS];
+            scores[winner.0] = WIN_SCORE;
+            return scores;
+        }
+        let mut scores = vec![0; PLAYERS];
+        for line in LINES {
+            let fields = line.map(|(x, y)| self.game_field[x][y]);
+            let owner = fields.iter().copied().find(|&field| field != 0);
+            if let Some(owner) = owner {
+                if fields.iter().all(|&field| field == 0 || field == owner) {
+                    let taken = fields.iter().filter(|&&field| field != 0).count();
+                    scores[owner - 1] += (taken * taken) as isize;
+                }
+            }
+        }
+        scores
+    }
+}
+
+impl TerminalState for ThreePlayerTicTacToe {
+    fn is_terminal(&self) -> bool {
+        self.winner().is_some() || self.game_field.iter().flatten().all(|&field| field != 0)
+    }
+}
+
+impl ThreePlayerTicTacToe {
+    fn winner(&self) -> Option<Player> {
+        LINES.iter().find_map(|line| {
+            let [a, b, c] = line.map(|(x, y)| self.game_field[x][y]);
+            (a != 0 && a == b && b == c).then(|| Player(a - 1))
+        })
+    }
+}
+
+impl Display for ThreePlayerTicTacToe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in self.game_field {
+            for field in row {
+                write!(f, "{} ", get_char(field))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+const fn get_char(field: usize) -> char {
+    match field {
+        1 => 'X',
+        2 => 'O',
+        3 => 'A',
+        _ => '.',
+    }
+}
+
+const fn gen_lines() -> [[(usize, usize); 3]; 24] {
+    let mut lines = [[(0, 0); 3]; 24];
+    let mut count = 0;
+    let mut x = 0;
+    while x < SIZE {
+        let mut y = 0;
+        while y + 2 < SIZE {
+            lines[count] = [(x, y), (x, y + 1), (x, y + 2)];
+            lines[count + 1] = [(y, x), (y + 1, x), (y + 2, x)];
+            count += 2;
+            y += 1;
+        }
+        x += 1;
+    }
+    let mut x = 0;
+    while x + 2 < SIZE {
+        let mut y = 0;
+        while y + 2 < SIZE {
+            lines[count] = [(x, y), (x + 1, y + 1), (x + 2, y + 2)];
+            lines[count + 1] = [(x, y + 2), (x + 1, y + 1), (x + 2, y)];
+            count += 2;
+            y += 1;
+        }
+        x += 1;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use game_engine::{
+        enhancements::{
+            base::alpha_beta,
+            multi_player::{max_n, paranoid},
+            search_state::SearchContext,
+        },
+        move_finders::{find_best_move_max_n, find_best_move_paranoid},
+        player::{Player, TwoPlayer},
+        traits::ChildStates,
+    };
+
+    use super::{super::super::impasse::game::Impasse, ThreePlayerTicTacToe};
+
+    /// Rows from the top, `X`, `O` and `A` for the players in turn order
+    fn board(rows: [&str; 4]) -> ThreePlayerTicTacToe {
+        let mut board = ThreePlayerTicTacToe::default();
+        for (x, row) in rows.iter().enumerate() {
+            for (y, field) in row.chars().enumerate() {
+                board.game_field[x][y] = match field {
+                    'X' => 1,
+                    'O' => 2,
+                    'A' => 3,
+                    _ => 0,
+                };
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn every_player_takes_an_immediate_win() {
+        for (player, field) in ['X', 'O', 'A'].into_iter().enumerate() {
+            let row = format!("{0}{0}..", field);
+            let state = board([&row, "....", "....", "...."]);
+            for depth in 1..=3 {
+                let max_n = find_best_move_max_n(&state, depth, Player(player));
+                let paranoid = find_best_move_paranoid(&state, depth, Player(player));
+                for result in [max_n, paranoid] {
+                    assert_eq!((result.best_move.x, result.best_move.y), (0, 2));
+                    assert_eq!(result.best_move.player, Player(player));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn paranoid_blocks_a_win_max_n_leaves_to_the_next_player() {
+        // A wins on the bottom row unless X or O takes the third field first. In max^n O blocks
+        // it to save its own score, paranoid assumes O lets A win.
+        let state = board(["X...", "...O", "....", "AA.."]);
+        let paranoid = find_best_move_paranoid(&state, 3, Player(0));
+        assert_eq!((paranoid.best_move.x, paranoid.best_move.y), (3, 2));
+        let max_n = find_best_move_max_n(&state, 3, Player(0));
+        assert_ne!((max_n.best_move.x, max_n.best_move.y), (3, 2));
+        let reply = find_best_move_max_n(&(state + max_n.best_move), 2, Player(1));
+        assert_eq!((reply.best_move.x, reply.best_move.y), (3, 2));
+    }
+
+    #[test]
+    fn two_player_adapter_matches_alpha_beta() {
+        let hash_field = Impasse::gen_hash_field(1);
+        let mut state = Impasse::new(&hash_field);
+        let mut color = true;
+        for ply in 0..4 {
+            let player = Player::from(color);
+            for depth in 1..=3 {
+                let context = &mut SearchContext::new();
+                let expected =
+                    alpha_beta(&state, depth, color, context, isize::MIN + 1, isize::MAX).unwrap();
+                let scores = max_n(&TwoPlayer(state), depth, player, context).unwrap();
+                assert_eq!(scores[0], expected);
+                let own = paranoid(
+                    &TwoPlayer(state),
+                    depth,
+                    player,
+                    player,
+                    context,
+                    isize::MIN + 1,
+                    isize::MAX,
+                );
+                let expected = match color {
+                    true => expected,
+                    false => -expected,
+                };
+                assert_eq!(own, Some(expected));
+            }
+            let moves = state.child_states(color);
+            state = state + moves[ply * 5 % moves.len()];
+            color = !color;
+        }
+    }
+}
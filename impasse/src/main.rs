@@ -1,23 +1,79 @@
 // Made by Philip Bollen
 extern crate rand;
-mod games;
-use game_engine::enhancements::{base::nega_with_table, transposition_table::TranspositionTable};
-use game_engine::move_finders::{find_best_move_t_tt_id, human_agent};
-use game_engine::traits::{ChildStates, ScoreOfState, StateHash, TerminalState};
-use games::impasse::game::Impasse;
+use game_engine::agents::{
+    Agent, BookAgent, HumanAgent, IterativeDeepeningAgent, MctsAgent, PonderingAgent,
+    TablebaseAgent,
+};
+use game_engine::clock::{Clock, TimeControl, TimeManager};
+use game_engine::enhancements::{
+    base::{alpha_beta_with_table_ordered, nega_with_table_ordered, nega_with_table_tablebase},
+    mate_score::mate_distance,
+    mcts::MctsConfig,
+    move_ordering::MoveOrdering,
+    opening_book::OpeningBook,
+    replacement_policy::ReplacementPolicy,
+    search_state::SearchContext,
+    transposition_table::TranspositionTable,
+};
+use game_engine::move_finders::find_best_move_t_tt_id;
+use game_engine::perft::{perft, perft_divide};
+use game_engine::runner::{play_game, play_game_observed, GameConfig, MoveRecord};
+use game_engine::tournament::{run_tournament, Engine, TournamentConfig};
+use game_engine::traits::{ChildStates, EndgameTablebase, ScoreOfState, StateHash, TerminalState};
+use impasse::games::impasse::{
+    actions::Move,
+    game::Impasse,
+    perft::REFERENCES,
+    tablebase::{Tablebase, MAX_PIECES},
+};
 
 use std::env;
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
 use std::process::exit;
-use std::time::Duration;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Seed of the Zobrist hash field, a saved table only fits the same seed
+const HASH_SEED: u64 = 420;
+const GAME_NAME: &str = "impasse";
+/// Loaded at the start of every game when it exists, written by `analyse`
+const TABLE_FILE: &str = "impasse_table.bin";
+/// Consulted before every search of the computer when it exists, written by `book`
+const BOOK_FILE: &str = "impasse_book.bin";
+/// Probed by the searches when it exists, written by `tablebase`
+const TABLEBASE_FILE: &str = "impasse_tablebase.bin";
 
 fn main() -> std::io::Result<()> {
     let args: Vec<String> = env::args().collect();
-    let set_time;
+    if args.len() > 1 && args[1] == "analyse" {
+        return analyse(args.get(2).and_then(|x| x.parse().ok()).unwrap_or(60));
+    }
+    if args.len() > 1 && args[1] == "perft" {
+        return run_perft(&args[2..]);
+    }
+    if args.len() > 1 && args[1] == "tablebase" {
+        return build_tablebase(args.get(2).and_then(|x| x.parse().ok()).unwrap_or(2));
+    }
+    let tablebase = load_tablebase();
+    if args.len() > 1 && args[1] == "book" {
+        return build_book(&args[2..], tablebase);
+    }
+    if args.len() > 1 && args[1] == "tournament" {
+        tournament(
+            args.get(2).and_then(|x| x.parse().ok()).unwrap_or(10),
+            args.get(3).and_then(|x| x.parse().ok()).unwrap_or(100),
+            args.get(4).and_then(|x| x.parse().ok()).unwrap_or(1),
+            tablebase,
+        );
+        return Ok(());
+    }
+    let clock;
     let save_to_file;
     let starting;
+    let threads;
+    let ponder;
     // !====================== Arg Parsing =====================================
     match args.len() {
         1 => {
@@ -25,116 +81,98 @@ fn main() -> std::io::Result<()> {
             exit(1);
         }
         2 => {
-            starting = match args[1].parse() {
-                Ok(x) => x,
-                Err(_) => true,
-            };
-            set_time = 200;
+            starting = args[1].parse().unwrap_or(true);
+            clock = Clock::per_move(Duration::from_millis(200));
             save_to_file = true;
+            threads = 1;
+            ponder = false;
         }
         3 => {
-            starting = match args[1].parse() {
-                Ok(x) => x,
-                Err(_) => true,
-            };
-            set_time = match args[2].parse() {
-                Ok(x) => x,
-                Err(_) => 200,
-            };
+            starting = args[1].parse().unwrap_or(true);
+            clock = parse_clock(&args[2]);
             save_to_file = true;
+            threads = 1;
+            ponder = false;
         }
         4 => {
-            starting = match args[1].parse() {
-                Ok(x) => x,
-                Err(_) => true,
-            };
-            set_time = match args[2].parse() {
-                Ok(x) => x,
-                Err(_) => 200,
-            };
-            save_to_file = match args[3].parse() {
-                Ok(x) => x,
-                Err(_) => true,
-            };
+            starting = args[1].parse().unwrap_or(true);
+            clock = parse_clock(&args[2]);
+            save_to_file = args[3].parse().unwrap_or(true);
+            threads = 1;
+            ponder = false;
+        }
+        5 => {
+            starting = args[1].parse().unwrap_or(true);
+            clock = parse_clock(&args[2]);
+            save_to_file = args[3].parse().unwrap_or(true);
+            threads = args[4].parse().unwrap_or(1).max(1);
+            ponder = false;
+        }
+        6 => {
+            starting = args[1].parse().unwrap_or(true);
+            clock = parse_clock(&args[2]);
+            save_to_file = args[3].parse().unwrap_or(true);
+            threads = args[4].parse().unwrap_or(1).max(1);
+            ponder = args[5].parse().unwrap_or(false);
         }
         _ => {
             starting = true;
-            set_time = 200;
+            clock = Clock::per_move(Duration::from_millis(200));
             save_to_file = true;
+            threads = 1;
+            ponder = false;
         }
     }
     // println!("Procces ID: {}\nStarting: ", id());
 
-    let binding = Impasse::gen_hash_field(420);
+    let binding = Impasse::gen_hash_field(HASH_SEED);
     let game = Impasse::new(&binding);
 
-    let mut current_move = game;
-    let mut color = true;
-    let mut time_of_color = 0;
-    let mut time_of_not_color = 0;
-    let mut number_of_moves: u16 = 0;
-    let mut table = TranspositionTable::default();
-    let mut game_history = Vec::new();
-    // let mut seed = StdRng::seed_from_u64(42);
-    let now = Instant::now();
-    while !current_move.is_terminal() {
-        {
-            match color {
-                true => println!("O is thinking"),
-                false => println!("X is thinking"),
-            }
-            number_of_moves += 1;
-
-            println!("{}", current_move);
-        }
-        let now = Instant::now();
-        let new_move = match color {
-            true => match starting {
-                true => computer_agent(&current_move, set_time, color, &mut table),
-                false => human_agent(&current_move, color),
-            },
-            false => match starting {
-                true => human_agent(&current_move, color),
-                false => computer_agent(&current_move, set_time, color, &mut table),
-            },
-        };
-        let stop = now.elapsed();
-        {
-            game_history.push((new_move, color));
-            current_move = current_move + new_move;
-            println!("{}", new_move);
-        }
-        {
-            if color {
-                time_of_color += stop.as_millis()
-            } else {
-                time_of_not_color += stop.as_millis()
-            }
-        }
-        color = !color;
-        {
-            if stop.as_millis() > 2000 {
-                println!("time: {}s", stop.as_secs());
-            } else if stop.as_micros() > 2000 {
-                println!("time: {}ms", stop.as_millis());
-            } else {
-                println!("time: {}us", stop.as_micros());
-            }
-            println!();
+    let table = Arc::new(TranspositionTable::default());
+    if Path::new(TABLE_FILE).exists() {
+        match table.load(TABLE_FILE, GAME_NAME, HASH_SEED) {
+            Ok(count) => println!("Loaded {} positions from {}", count, TABLE_FILE),
+            Err(error) => println!("Ignoring {}: {}", TABLE_FILE, error),
         }
     }
-    let stop = now.elapsed();
+    let book = match Path::new(BOOK_FILE).exists() {
+        true => match OpeningBook::load(BOOK_FILE, GAME_NAME, HASH_SEED) {
+            Ok(book) => {
+                println!("Loaded {} book positions from {}", book.len(), BOOK_FILE);
+                book
+            }
+            Err(error) => {
+                println!("Ignoring {}: {}", BOOK_FILE, error);
+                OpeningBook::new()
+            }
+        },
+        false => OpeningBook::new(),
+    };
+    let searcher = BookAgent::new(
+        IterativeDeepeningAgent::new(table.clone(), nega_search(tablebase.clone()), threads),
+        Arc::new(book),
+        rand::random(),
+    );
+    let computer: Box<dyn Agent<Impasse, Move>> = match tablebase.clone() {
+        Some(tablebase) => Box::new(TablebaseAgent::new(searcher, tablebase)),
+        None => Box::new(searcher),
+    };
+    // The human is wrapped, so the computer searches the expected reply while the human thinks
+    let human: Box<dyn Agent<Impasse, Move>> = match ponder {
+        true => Box::new(PonderingAgent::new(
+            HumanAgent,
+            table.clone(),
+            nega_search(tablebase),
+        )),
+        false => Box::new(HumanAgent),
+    };
+    let agents = match starting {
+        true => [computer, human],
+        false => [human, computer],
+    };
+    let game_history = play(game, agents, &clock);
+    println!("Transposition table:\n{}", table.statistics());
 
-    match color {
-        true => println!("X wins"),
-        false => println!("O wins"),
-    }
-    {
-        println!("stopped with {} turns", number_of_moves);
-        println!("total time played: {}s", stop.as_secs());
-        println!("'O' time: {}s", time_of_color / 1000);
-        println!("'X' time: {}s", time_of_not_color / 1000);
-    }
     if save_to_file {
         let mut file = File::create("Game.txt")?;
         for single_move in game_history {
@@ -143,17 +181,143 @@ fn main() -> std::io::Result<()> {
             } else {
                 writeln!(file, "X: {}\n", single_move.0).unwrap();
             }
+            if !single_move.2.is_empty() {
+                writeln!(file, "Expected continuation:").unwrap();
+                for next_move in single_move.2 {
+                    writeln!(file, "{}", next_move).unwrap();
+                }
+            }
         }
     }
     Ok(())
 }
 
+/// Plays a game with `agents[0]` as `true` and `agents[1]` as `false`, returns every move with
+/// its color and the continuation the agent expected
+fn play<'game>(
+    game: Impasse<'game>,
+    mut agents: [Box<dyn Agent<Impasse<'game>, Move> + '_>; 2],
+    clock: &Clock,
+) -> Vec<(Move, bool, Vec<Move>)> {
+    let config = GameConfig {
+        clocks: [*clock; 2],
+        max_moves: usize::MAX,
+    };
+    println!("O is thinking");
+    println!("{}", game);
+    let [first, second] = &mut agents;
+    let outcome = play_game_observed(
+        &game,
+        [first.as_mut(), second.as_mut()],
+        &config,
+        |current_move, record| {
+            if let Some(result) = &record.search {
+                let score = match record.color {
+                    true => result.score,
+                    false => -result.score,
+                };
+                println!(
+                    "Thinking {}ply deep, {}, {} nodes",
+                    result.depth,
+                    describe_score(score),
+                    result.nodes
+                );
+            }
+            println!("{}", record.played);
+            let continuation = continuation(record);
+            if !continuation.is_empty() {
+                println!("Expected continuation:");
+                for next_move in &continuation {
+                    println!("{}", next_move);
+                }
+            }
+            {
+                let stop = record.elapsed;
+                if stop.as_millis() > 2000 {
+                    println!("time: {}s", stop.as_secs());
+                } else if stop.as_micros() > 2000 {
+                    println!("time: {}ms", stop.as_millis());
+                } else {
+                    println!("time: {}us", stop.as_micros());
+                }
+                if !matches!(record.clock.control(), TimeControl::PerMove(_)) {
+                    println!("clock: {}ms left", record.clock.remaining().as_millis());
+                }
+                println!();
+            }
+            if !current_move.is_terminal() {
+                match record.color {
+                    true => println!("X is thinking"),
+                    false => println!("O is thinking"),
+                }
+                println!("{}", current_move);
+            }
+        },
+    );
+
+    match outcome.winner() {
+        Some(true) => println!("O wins"),
+        Some(false) => println!("X wins"),
+        None => println!("Draw"),
+    }
+    {
+        let total = outcome.time_of(true) + outcome.time_of(false);
+        println!("stopped with {} turns", outcome.moves.len());
+        println!("total time played: {}s", total.as_secs());
+        println!("'O' time: {}s", outcome.time_of(true).as_secs());
+        println!("'X' time: {}s", outcome.time_of(false).as_secs());
+    }
+    outcome
+        .moves
+        .iter()
+        .map(|record| (record.played, record.color, continuation(record)))
+        .collect()
+}
+
+/// `<ms>` per move, `<base_ms>+<increment_ms>` or `<moves>/<base_ms>`, 200ms per move when the
+/// argument doesn't parse
+fn parse_clock(argument: &str) -> Clock {
+    let parsed = if let Some((base, increment)) = argument.split_once('+') {
+        base.parse()
+            .ok()
+            .zip(increment.parse().ok())
+            .map(|(base, increment)| {
+                Clock::with_increment(
+                    Duration::from_millis(base),
+                    Duration::from_millis(increment),
+                )
+            })
+    } else if let Some((moves, base)) = argument.split_once('/') {
+        moves
+            .parse()
+            .ok()
+            .zip(base.parse().ok())
+            .map(|(moves, base)| Clock::with_moves_to_go(Duration::from_millis(base), moves))
+    } else {
+        argument
+            .parse()
+            .ok()
+            .map(|time| Clock::per_move(Duration::from_millis(time)))
+    };
+    parsed.unwrap_or(Clock::per_move(Duration::from_millis(200)))
+}
+
+/// The line the agent expected after its move, the first move of the pv is the move itself
+fn continuation(record: &MoveRecord<Move>) -> Vec<Move> {
+    match &record.search {
+        Some(result) => result.pv[1..].to_vec(),
+        None => Vec::new(),
+    }
+}
+
 fn help() {
     println!("This is the help menu of the game");
     println!("=================================");
     println!("The arguments are structured as following");
-    println!("impasse.exe <starting:bool><set_time:uint><save_game:bool>");
-    println!("");
+    println!(
+        "impasse.exe <starting:bool><set_time:uint><save_game:bool><threads:uint><ponder:bool>"
+    );
+    println!();
     println!("Types:");
     println!("\tbool:");
     println!("\t\ttrue \t-> passing a true value");
@@ -162,23 +326,32 @@ fn help() {
     println!(
         "\t\tprovide a number between 1 and 2^64 \n\t\tThis is used for time in ms so be nice to yourself😉\n\t\tJust so you know, 100ms is about 7ply deep in mid game, 200ms is the default"
     );
-    println!("");
+    println!();
     println!("Arguments:");
     println!("\tstarting -> determines if the computer is to start or not");
     println!("\t\ttrue -> computer starts");
     println!("\t\tfalse -> player starts");
-    println!("");
+    println!();
     println!("\tset_time -> the time (in milli seconds) the computer is given to search for the best move");
-    println!("");
+    println!("\t\tor a game clock for both sides, where the computer decides how long to think:");
+    println!("\t\t<base>+<increment> -> base time for the game and an increment after every move");
+    println!("\t\t<moves>/<base> -> base time for every <moves> moves");
+    println!();
     println!(
         "\tsave_game -> The program provides an option to save the game to a \"game.txt\" file"
     );
-    println!("");
+    println!();
+    println!("\tthreads -> the number of threads the computer searches with");
+    println!();
+    println!("\tponder -> the computer searches the move it expects from you while you think");
+    println!();
     println!("Default Values:");
     println!("\tstarting = true");
     println!("\tset_time = 200 milli seconds");
     println!("\tsave_game = true");
-    println!("");
+    println!("\tthreads = 1");
+    println!("\tponder = false");
+    println!();
     println!("Examples:");
     println!("\t>impasse.exe lets play; # Use the default values");
     println!("\t>impasse.exe y; # use the default values");
@@ -186,8 +359,13 @@ fn help() {
     println!(
         "\t>impasse.exe false 400; # the player will start, computer can think 400ms per turn"
     );
+    println!("\t>impasse.exe true 300000+2000; # 5 minutes for the game, 2s more after every move");
     println!("\t>impasse.exe true 1000 false; # the computer will start, computer can think 1s per turn, and the game will not be saved to a file.");
-    println!("");
+    println!(
+        "\t>impasse.exe true 1000 true 4; # the same, but the computer searches with 4 threads"
+    );
+    println!("\t>impasse.exe true 1000 true 1 true; # the computer also thinks while you do");
+    println!();
     println!("During the game:");
     println!("\tThe player will be provided with all possible moves ranging from 1 to x. Where x is the last move");
     println!(
@@ -196,25 +374,358 @@ fn help() {
     println!(
         "\tand hitting [Enter], this will execute the move and directly pass the turn to the computer."
     );
+    println!();
+    println!("Analysis:");
+    println!("\t>impasse.exe analyse 3600; # search the opening for an hour and save the table to \"{}\"", TABLE_FILE);
+    println!("\tThis table is loaded at the start of every following game.");
+    println!();
+    println!("Opening book:");
+    println!("\t>impasse.exe book analyse 2 6 10; # analyse the first 2 plies 6ply deep, keep moves at most 10 behind the best");
+    println!("\t>impasse.exe book selfplay 20 100 8; # add the first 8 moves of 20 self-play games at 100ms per move");
+    println!(
+        "\tThe book is saved to \"{}\" and the computer plays from it before it searches.",
+        BOOK_FILE
+    );
+    println!();
+    println!("Endgame tablebase:");
+    println!("\t>impasse.exe tablebase 2; # solve every state with at most 2 pieces per side");
+    println!(
+        "\tThe results are saved to \"{}\", the computer plays them perfectly and its search uses them.",
+        TABLEBASE_FILE
+    );
+    println!();
+    println!("Tournament:");
+    println!(
+        "\t>impasse.exe tournament 20 100 4; # 20 games per pairing, 100ms per move, 4 threads"
+    );
+    println!("\tPlays nega_with_table, alpha_beta_with_table at twice the time and MCTS against each other.");
+    println!();
+    println!("Move generation:");
+    println!("\t>impasse.exe perft; # check the move generation against the reference node counts");
+    println!("\t>impasse.exe perft 5; # count the move sequences of 5 plies from the start");
+    println!("\t>impasse.exe perft 5 divide; # the same, split by first move");
     println!("\n\n Happy playing 😊");
 }
 
-fn computer_agent<
-    T: ScoreOfState + TerminalState + ChildStates<M> + Sync + Eq + Copy + StateHash,
-    M: Copy + Send + Default + Ord,
->(
-    current_move: &T,
-    set_time: u64,
+/// A score from the view of the side it is for, wins and losses are shown in plies
+fn describe_score(score: isize) -> String {
+    match mate_distance(score) {
+        Some(plies) if plies >= 0 => format!("win in {} plies", plies),
+        Some(plies) => format!("loss in {} plies", -plies),
+        None => format!("score {}", score),
+    }
+}
+
+/// Loads the tablebase the searches probe, `None` without a usable `TABLEBASE_FILE`
+fn load_tablebase() -> Option<Arc<Tablebase>> {
+    if !Path::new(TABLEBASE_FILE).exists() {
+        return None;
+    }
+    match Tablebase::load(TABLEBASE_FILE) {
+        Ok(tablebase) => {
+            println!(
+                "Loaded the tablebase for {} pieces per side from {}",
+                tablebase.pieces(),
+                TABLEBASE_FILE
+            );
+            Some(Arc::new(tablebase))
+        }
+        Err(error) => {
+            println!("Ignoring {}: {}", TABLEBASE_FILE, error);
+            None
+        }
+    }
+}
+
+/// `nega_for_true` owning the tablebase its leaves probe, in the form the move finders expect
+fn nega_search(
+    tablebase: Option<Arc<Tablebase>>,
+) -> impl Fn(
+    &Impasse<'_>,
+    isize,
+    bool,
+    &TranspositionTable<Move>,
+    &mut MoveOrdering<Move>,
+    &mut SearchContext,
+) -> Option<isize>
+       + Clone
+       + Send
+       + Sync {
+    move |state, depth, color, table, ordering, context| {
+        nega_for_true(
+            state,
+            depth,
+            color,
+            table,
+            tablebase.as_deref(),
+            ordering,
+            context,
+        )
+    }
+}
+
+/// `nega_with_table` with killer, history and countermove ordering and the leaves from
+/// `tablebase` if there is one, from the view of `true`, which is what the move finders expect
+fn nega_for_true<M, T, B>(
+    state: &T,
+    depth: isize,
     color: bool,
-    table: &mut TranspositionTable<M>,
-) -> M {
-    find_best_move_t_tt_id(
-        current_move,
-        Duration::from_millis(set_time),
+    table: &TranspositionTable<M>,
+    tablebase: Option<&B>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+    B: EndgameTablebase<T>,
+{
+    let (alpha, beta) = (isize::MIN + 1, isize::MAX);
+    let score = match tablebase {
+        Some(tablebase) => nega_with_table_tablebase(
+            state, depth, color, table, tablebase, ordering, context, alpha, beta,
+        ),
+        None => nega_with_table_ordered(state, depth, color, table, ordering, context, alpha, beta),
+    }?;
+    Some(match color {
+        true => score,
+        false => -score,
+    })
+}
+
+/// `alpha_beta_with_table` with killer, history and countermove ordering, it already scores from
+/// the view of `true`
+fn alpha_beta_for_true<M, T>(
+    state: &T,
+    depth: isize,
+    color: bool,
+    table: &TranspositionTable<M>,
+    ordering: &mut MoveOrdering<M>,
+    context: &mut SearchContext,
+) -> Option<isize>
+where
+    M: Default + Copy + Eq + Ord,
+    T: ScoreOfState + TerminalState + ChildStates<M> + Copy + StateHash,
+{
+    alpha_beta_with_table_ordered(
+        state,
+        depth,
         color,
         table,
-        |state, depth, color, table| {
-            -nega_with_table(state, depth, color, table, isize::MIN + 1, isize::MAX)
-        },
+        ordering,
+        context,
+        isize::MIN + 1,
+        isize::MAX,
     )
 }
+
+/// Plays the engines against each other from the starting position and prints the results
+fn tournament(games: usize, set_time: u64, threads: usize, tablebase: Option<Arc<Tablebase>>) {
+    let binding = Impasse::gen_hash_field(HASH_SEED);
+    let game = Impasse::new(&binding);
+    let clock = Clock::per_move(Duration::from_millis(set_time));
+    let slow_clock = Clock::per_move(Duration::from_millis(set_time * 2));
+    // Small tables, every game gets its own
+    let engines = [
+        Engine::new("nega_with_table", clock, move || {
+            Box::new(IterativeDeepeningAgent::new(
+                Arc::new(TranspositionTable::new(16, ReplacementPolicy::TwoTier)),
+                nega_search(tablebase.clone()),
+                1,
+            )) as Box<dyn Agent<Impasse, Move>>
+        }),
+        Engine::new("alpha_beta_with_table", slow_clock, || {
+            Box::new(IterativeDeepeningAgent::new(
+                Arc::new(TranspositionTable::new(16, ReplacementPolicy::TwoTier)),
+                alpha_beta_for_true,
+                1,
+            )) as Box<dyn Agent<Impasse, Move>>
+        }),
+        Engine::new("mcts", clock, || {
+            Box::new(MctsAgent::new(MctsConfig::default())) as Box<dyn Agent<Impasse, Move>>
+        }),
+    ];
+    let config = TournamentConfig {
+        games_per_pairing: games,
+        threads: threads.max(1),
+        ..TournamentConfig::default()
+    };
+    println!(
+        "Playing {} games per pairing at {}ms per move on {} threads",
+        games, set_time, config.threads
+    );
+    print!("{}", run_tournament(&game, &engines, &config));
+}
+
+/// Extends the book with `analyse <plies> <depth> <margin>` or `selfplay <games> <ms> <plies>`
+fn build_book(args: &[String], tablebase: Option<Arc<Tablebase>>) -> std::io::Result<()> {
+    let binding = Impasse::gen_hash_field(HASH_SEED);
+    let game = Impasse::new(&binding);
+    let mut book = match Path::new(BOOK_FILE).exists() {
+        true => OpeningBook::load(BOOK_FILE, GAME_NAME, HASH_SEED)?,
+        false => OpeningBook::new(),
+    };
+    let number = |index: usize, default: usize| {
+        args.get(index)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(default)
+    };
+    match args.first().map(String::as_str) {
+        Some("selfplay") => {
+            let games = number(1, 20);
+            let set_time = number(2, 100) as u64;
+            let plies = number(3, 8);
+            let first_moves = game.child_states(true);
+            let config = GameConfig {
+                clocks: [Clock::per_move(Duration::from_millis(set_time)); 2],
+                ..GameConfig::default()
+            };
+            for index in 0..games {
+                // Every game opens with another move, so the games differ
+                let mut opening = OpeningBook::new();
+                opening.add(&game, true, first_moves[index % first_moves.len()], 1);
+                let mut first = BookAgent::new(
+                    IterativeDeepeningAgent::new(
+                        Arc::new(TranspositionTable::new(16, ReplacementPolicy::TwoTier)),
+                        nega_search(tablebase.clone()),
+                        1,
+                    ),
+                    Arc::new(opening),
+                    0,
+                );
+                let mut second = IterativeDeepeningAgent::new(
+                    Arc::new(TranspositionTable::new(16, ReplacementPolicy::TwoTier)),
+                    nega_search(tablebase.clone()),
+                    1,
+                );
+                let outcome = play_game(&game, [&mut first, &mut second], &config);
+                println!(
+                    "Game {}: {:?} after {} moves",
+                    index + 1,
+                    outcome.result,
+                    outcome.moves.len()
+                );
+                book.add_game(&game, &outcome, plies);
+            }
+        }
+        _ => {
+            let plies = number(1, 2);
+            let depth = number(2, 6) as isize;
+            let margin = number(3, 10) as isize;
+            println!("Analysing {} plies of the opening {}ply deep", plies, depth);
+            let table = TranspositionTable::default();
+            let search = nega_search(tablebase);
+            book.add_analysis(&game, true, plies, depth, margin, &table, &search);
+        }
+    }
+    book.save(BOOK_FILE, GAME_NAME, HASH_SEED)?;
+    println!("Saved {} book positions to {}", book.len(), BOOK_FILE);
+    Ok(())
+}
+
+/// Solves every state with at most `pieces` pieces per side and saves the results
+fn build_tablebase(pieces: usize) -> std::io::Result<()> {
+    if !(1..=MAX_PIECES).contains(&pieces) {
+        println!("A tablebase has 1 to {} pieces per side", MAX_PIECES);
+        exit(1);
+    }
+    println!(
+        "Solving every state with at most {} pieces per side",
+        pieces
+    );
+    let start = Instant::now();
+    let tablebase = Tablebase::generate(pieces);
+    println!(
+        "Solved {} states in {}s",
+        tablebase.size(),
+        start.elapsed().as_secs()
+    );
+    tablebase.save(TABLEBASE_FILE)?;
+    println!("Saved the tablebase to {}", TABLEBASE_FILE);
+    Ok(())
+}
+
+/// Checks the reference counts without arguments, otherwise counts `<depth>` plies from the
+/// start, split by first move with `divide`
+fn run_perft(args: &[String]) -> std::io::Result<()> {
+    let binding = Impasse::gen_hash_field(HASH_SEED);
+    let Some(depth) = args.first().and_then(|x| x.parse::<usize>().ok()) else {
+        let mut failed = false;
+        for reference in &REFERENCES {
+            let mut game = Impasse::new(&binding);
+            game.game_field = reference.game_field();
+            for (index, &expected) in reference.counts.iter().enumerate() {
+                let nodes = perft(&game, index + 1, reference.color);
+                let verdict = match nodes == expected {
+                    true => "OK",
+                    false => "FAIL",
+                };
+                failed |= nodes != expected;
+                println!(
+                    "{} {} depth {}: {} nodes, expected {}",
+                    verdict,
+                    reference.name,
+                    index + 1,
+                    nodes,
+                    expected
+                );
+            }
+        }
+        if failed {
+            println!("The move generation differs from the reference counts");
+            exit(1);
+        }
+        println!("The move generation matches the reference counts");
+        return Ok(());
+    };
+    let game = Impasse::new(&binding);
+    let start = Instant::now();
+    let nodes = match args.get(1).map(String::as_str) {
+        Some("divide") => {
+            let mut nodes = 0;
+            for (root_move, count) in perft_divide(&game, depth, true) {
+                // A move is shown over several lines
+                println!("{} nodes after {}", count, root_move);
+                nodes += count;
+            }
+            nodes
+        }
+        _ => perft(&game, depth, true),
+    };
+    let elapsed = start.elapsed();
+    println!(
+        "perft {}: {} nodes in {}ms, {:.0} nodes/s",
+        depth,
+        nodes,
+        elapsed.as_millis(),
+        nodes as f64 / elapsed.as_secs_f64()
+    );
+    Ok(())
+}
+
+/// Searches the starting position for `seconds` and saves the table for later games
+fn analyse(seconds: u64) -> std::io::Result<()> {
+    let binding = Impasse::gen_hash_field(HASH_SEED);
+    let game = Impasse::new(&binding);
+    let table = TranspositionTable::default();
+    if Path::new(TABLE_FILE).exists() {
+        table.load(TABLE_FILE, GAME_NAME, HASH_SEED)?;
+    }
+    println!("Analysing the opening for {}s", seconds);
+    let result = find_best_move_t_tt_id(
+        &game,
+        TimeManager::fixed(Duration::from_secs(seconds)),
+        true,
+        &table,
+        &nega_search(None),
+    );
+    println!(
+        "Searched {}ply deep, {}, {} nodes",
+        result.depth,
+        describe_score(result.score),
+        result.nodes
+    );
+    table.save(TABLE_FILE, GAME_NAME, HASH_SEED)?;
+    println!("Saved {} positions to {}", table.size(), TABLE_FILE);
+    Ok(())
+}